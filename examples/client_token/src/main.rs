@@ -7,12 +7,13 @@ use std::error::Error;
 
 fn main() {
     let merchant_id = std::env::var("MERCHANT_ID").expect("environment variable MERCHANT_ID is not defined");
-    let bt = Braintree::new(
-        Environment::Sandbox,
-        merchant_id.clone(),
-        std::env::var("PUBLIC_KEY").expect("environment variable PUBLIC_KEY is not defined"),
-        std::env::var("PRIVATE_KEY").expect("environment variable PRIVATE_KEY is not defined"),
-    );
+    let bt = Braintree::builder()
+        .environment(Environment::Sandbox)
+        .merchant_id(merchant_id.clone())
+        .public_key(std::env::var("PUBLIC_KEY").expect("environment variable PUBLIC_KEY is not defined"))
+        .private_key(std::env::var("PRIVATE_KEY").expect("environment variable PRIVATE_KEY is not defined"))
+        .build()
+        .expect("failed to create Braintree handle");
 
     let result = bt.client_token().generate(client_token::Request{
         // Uncomment the following line with a valid Braintree customer id to generate a customer-specific client token.
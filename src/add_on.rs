@@ -0,0 +1,54 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
+
+/// An add-on, which increases the price of a subscription.
+///
+/// These are defined in the Control Panel, and can be attached to plans and
+/// subscriptions. When returned as part of a subscription, the values reflect
+/// any overrides made for that subscription, and `id` refers to the
+/// add-on it was inherited from.
+///
+/// Add-ons have no billing frequency or trial period of their own. They're
+/// charged on each billing cycle of the subscription they belong to, for
+/// `number_of_billing_cycles` cycles, or indefinitely if `never_expires` is
+/// set.
+#[derive(Debug)]
+pub struct AddOn {
+    pub id: String,
+    pub amount: Option<String>,
+    pub created_at: Option<String>,
+    pub currency_iso_code: Option<String>,
+    /// The billing cycle that the subscription is currently on, if any.
+    pub current_billing_cycle: Option<u32>,
+    pub description: Option<String>,
+    pub kind: Option<String>,
+    pub merchant_id: Option<String>,
+    pub name: Option<String>,
+    pub never_expires: Option<bool>,
+    pub number_of_billing_cycles: Option<u32>,
+    pub quantity: Option<u32>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for AddOn {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<AddOn> {
+        Ok(AddOn{
+            id: ::require_text(root, "id")?,
+            amount: ::find_text(root, "amount"),
+            created_at: ::find_text(root, "created-at"),
+            currency_iso_code: ::find_text(root, "currency-iso-code"),
+            current_billing_cycle: ::parse_text(root, "current-billing-cycle")?,
+            description: ::find_text(root, "description"),
+            kind: ::find_text(root, "kind"),
+            merchant_id: ::find_text(root, "merchant-id"),
+            name: ::find_text(root, "name"),
+            never_expires: ::find_text(root, "never-expires").map(|s| s == "true"),
+            number_of_billing_cycles: ::parse_text(root, "number-of-billing-cycles")?,
+            quantity: ::parse_text(root, "quantity")?,
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
+}
@@ -1,6 +1,16 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
 use std::fmt::Write;
 use xml;
 
+/// A record containing address details.
+///
+/// Addresses can either be sent inline as the billing or shipping address on
+/// a `transaction::Request`, or stored in the vault against a customer using
+/// the `AddressGateway`. The `id`, `created_at`, `customer_id` and
+/// `updated_at` fields are assigned by the gateway and are ignored when
+/// sending a request.
 #[derive(Debug, Default)]
 pub struct Address {
     pub company: Option<String>,
@@ -15,6 +25,35 @@ pub struct Address {
     pub postal_code: Option<String>,
     pub region: Option<String>,
     pub street_address: Option<String>,
+    pub created_at: Option<String>,
+    pub customer_id: Option<String>,
+    pub id: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for Address {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Address> {
+        Ok(Address{
+            company: ::find_text(root, "company"),
+            country_code_alpha2: ::find_text(root, "country-code-alpha2"),
+            country_code_alpha3: ::find_text(root, "country-code-alpha3"),
+            country_code_numeric: ::find_text(root, "country-code-numeric"),
+            country_name: ::find_text(root, "country-name"),
+            extended_address: ::find_text(root, "extended-address"),
+            first_name: ::find_text(root, "first-name"),
+            last_name: ::find_text(root, "last-name"),
+            locality: ::find_text(root, "locality"),
+            postal_code: ::find_text(root, "postal-code"),
+            region: ::find_text(root, "region"),
+            street_address: ::find_text(root, "street-address"),
+            created_at: ::find_text(root, "created-at"),
+            customer_id: ::find_text(root, "customer-id"),
+            id: ::find_text(root, "id"),
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
 }
 
 impl ::ToXml for Address {
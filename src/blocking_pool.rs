@@ -0,0 +1,312 @@
+//! A wrapper that runs blocking Braintree calls on Tokio's blocking thread
+//! pool, available with the `blocking-pool` feature.
+//!
+//! `BlockingPoolBraintree` exposes the same gateways as `Braintree`, taking
+//! and returning the same request and response types, but each method returns
+//! a future so that it can be awaited from async code without stalling the
+//! runtime's worker threads.
+//!
+//! This is a convenience wrapper over the blocking client, and does no
+//! asynchronous I/O of its own. Each call simply runs the blocking method,
+//! using the configured `HttpTransport`, inside `tokio::task::spawn_blocking`.
+//! The pool thread is held for the whole request, including any retry backoff
+//! sleeps, so the number of requests in flight is limited by the size of the
+//! blocking pool. Methods must be called from within a Tokio runtime.
+//!
+//! ```rust,edition2018,no_run
+//! extern crate braintree;
+//!
+//! use braintree::{BlockingPoolBraintree, Braintree, Environment};
+//!
+//! async fn generate_token() -> braintree::error::Result<String> {
+//!     let bt = BlockingPoolBraintree::new(Braintree::builder()
+//!         .environment(Environment::Sandbox)
+//!         .merchant_id("<merchant_id>")
+//!         .public_key("<public_key>")
+//!         .private_key("<private_key>")
+//!         .build()?);
+//!     let token = bt.client_token().generate(Default::default()).await?;
+//!     Ok(token.value)
+//! }
+//! # fn main() {}
+//! ```
+
+use add_on;
+use address;
+use client_token;
+use credit_card;
+use customer;
+use discount;
+use error;
+use payment_method;
+use payment_method_nonce;
+use plan;
+use refund;
+use settlement;
+use subscription;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio;
+use transaction;
+use Braintree;
+
+/// A handle to the Braintree API whose methods return futures.
+///
+/// This is cheap to clone, and clones share the same underlying `Braintree`.
+#[derive(Clone)]
+pub struct BlockingPoolBraintree(Arc<Braintree>);
+
+impl BlockingPoolBraintree {
+    /// Wrap a blocking handle so that its calls run on the blocking pool.
+    pub fn new(bt: Braintree) -> BlockingPoolBraintree {
+        BlockingPoolBraintree(Arc::new(bt))
+    }
+
+    /// The blocking handle used to service requests.
+    pub fn blocking(&self) -> &Braintree {
+        &self.0
+    }
+
+    pub fn add_on(&self) -> AddOnGateway {
+        AddOnGateway(self.0.clone())
+    }
+
+    pub fn address(&self) -> AddressGateway {
+        AddressGateway(self.0.clone())
+    }
+
+    pub fn client_token(&self) -> ClientTokenGateway {
+        ClientTokenGateway(self.0.clone())
+    }
+
+    pub fn credit_card(&self) -> CreditCardGateway {
+        CreditCardGateway(self.0.clone())
+    }
+
+    pub fn customer(&self) -> CustomerGateway {
+        CustomerGateway(self.0.clone())
+    }
+
+    pub fn discount(&self) -> DiscountGateway {
+        DiscountGateway(self.0.clone())
+    }
+
+    pub fn payment_method(&self) -> PaymentMethodGateway {
+        PaymentMethodGateway(self.0.clone())
+    }
+
+    pub fn payment_method_nonce(&self) -> PaymentMethodNonceGateway {
+        PaymentMethodNonceGateway(self.0.clone())
+    }
+
+    pub fn plan(&self) -> PlanGateway {
+        PlanGateway(self.0.clone())
+    }
+
+    pub fn subscription(&self) -> SubscriptionGateway {
+        SubscriptionGateway(self.0.clone())
+    }
+
+    pub fn transaction(&self) -> TransactionGateway {
+        TransactionGateway(self.0.clone())
+    }
+
+    pub fn testing(&self) -> TestingGateway {
+        TestingGateway(self.0.clone())
+    }
+}
+
+impl From<Braintree> for BlockingPoolBraintree {
+    fn from(bt: Braintree) -> BlockingPoolBraintree {
+        BlockingPoolBraintree::new(bt)
+    }
+}
+
+/// The future returned by a wrapped gateway method, resolving to the
+/// same result that the blocking method would return. If the blocking task
+/// panics or is cancelled, this resolves to `Error::TaskFailed` instead.
+pub struct ApiCall<T>(tokio::task::JoinHandle<error::Result<T>>);
+
+impl<T> Future for ApiCall<T> {
+    type Output = error::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<error::Result<T>> {
+        match Pin::new(&mut self.0).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(error::Error::TaskFailed(err.to_string()))),
+        }
+    }
+}
+
+fn spawn<T, F>(bt: &Arc<Braintree>, f: F) -> ApiCall<T>
+    where T: Send + 'static, F: FnOnce(&Braintree) -> error::Result<T> + Send + 'static
+{
+    let bt = bt.clone();
+    ApiCall(tokio::task::spawn_blocking(move || f(&bt)))
+}
+
+macro_rules! pooled_gateway {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident => $accessor:ident {
+            $(
+                $(#[$method_attr:meta])*
+                pub fn $method:ident($($arg:ident: $typ:ty),*) -> $output:ty;
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        pub struct $name(Arc<Braintree>);
+
+        impl $name {
+            $(
+                $(#[$method_attr])*
+                pub fn $method(&self, $($arg: $typ),*) -> ApiCall<$output> {
+                    spawn(&self.0, move |bt| bt.$accessor().$method($($arg),*))
+                }
+            )*
+        }
+    };
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::AddOnGateway`.
+    pub struct AddOnGateway => add_on {
+        pub fn all() -> Vec<add_on::AddOn>;
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::AddressGateway`.
+    pub struct AddressGateway => address {
+        pub fn create(customer_id: String, address: address::Address) -> address::Address;
+        pub fn find(customer_id: String, address_id: String) -> address::Address;
+        pub fn update(customer_id: String, address_id: String, address: address::Address) -> address::Address;
+        pub fn delete(customer_id: String, address_id: String) -> ();
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::ClientTokenGateway`.
+    pub struct ClientTokenGateway => client_token {
+        pub fn generate(req: client_token::Request) -> client_token::ClientToken;
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::CreditCardGateway`.
+    pub struct CreditCardGateway => credit_card {
+        pub fn create(credit_card: credit_card::CreditCard) -> credit_card::CreditCard;
+        pub fn find(token: String) -> credit_card::CreditCard;
+        pub fn update(token: String, credit_card: credit_card::CreditCard) -> credit_card::CreditCard;
+        pub fn delete(token: String) -> ();
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::CustomerGateway`.
+    pub struct CustomerGateway => customer {
+        pub fn create(customer: customer::Request) -> customer::Customer;
+        pub fn find(customer_id: String) -> customer::Customer;
+        pub fn update(customer_id: String, customer: customer::Request) -> customer::Customer;
+        pub fn delete(customer_id: String) -> ();
+    }
+}
+
+impl CustomerGateway {
+    /// Search for customers matching the given criteria. Unlike the blocking
+    /// version, this fetches every page of results before resolving.
+    pub fn search(&self, query: customer::Search) -> ApiCall<Vec<customer::Customer>> {
+        spawn(&self.0, move |bt| bt.customer().search(query)?.collect())
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::DiscountGateway`.
+    pub struct DiscountGateway => discount {
+        pub fn all() -> Vec<discount::Discount>;
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::PaymentMethodGateway`.
+    pub struct PaymentMethodGateway => payment_method {
+        pub fn create(payment_method: payment_method::Request) -> payment_method::PaymentMethod;
+        pub fn find(token: String) -> payment_method::PaymentMethod;
+        pub fn update(token: String, payment_method: payment_method::Request) -> payment_method::PaymentMethod;
+        pub fn delete(token: String) -> ();
+        pub fn grant(grant: payment_method::GrantRequest) -> payment_method_nonce::PaymentMethodNonce;
+        pub fn revoke(token: String) -> ();
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::PaymentMethodNonceGateway`.
+    pub struct PaymentMethodNonceGateway => payment_method_nonce {
+        pub fn create(payment_method_token: String) -> payment_method_nonce::PaymentMethodNonce;
+        pub fn find(nonce: String) -> payment_method_nonce::PaymentMethodNonce;
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::PlanGateway`.
+    pub struct PlanGateway => plan {
+        pub fn all() -> Vec<plan::Plan>;
+        pub fn create(plan: plan::Request) -> plan::Plan;
+        pub fn find(plan_id: String) -> plan::Plan;
+        pub fn update(plan_id: String, plan: plan::Request) -> plan::Plan;
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::SubscriptionGateway`.
+    pub struct SubscriptionGateway => subscription {
+        pub fn create(subscription: subscription::Request) -> subscription::Subscription;
+        pub fn find(subscription_id: String) -> subscription::Subscription;
+        pub fn update(subscription_id: String, subscription: subscription::Request) -> subscription::Subscription;
+        pub fn cancel(subscription_id: String) -> subscription::Subscription;
+        pub fn retry_charge(charge: subscription::RetryCharge) -> transaction::Transaction;
+    }
+}
+
+impl SubscriptionGateway {
+    /// Search for subscriptions matching the given criteria. Unlike the
+    /// blocking version, this fetches every page of results before resolving.
+    pub fn search(&self, query: subscription::Search) -> ApiCall<Vec<subscription::Subscription>> {
+        spawn(&self.0, move |bt| bt.subscription().search(query)?.collect())
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::TransactionGateway`.
+    pub struct TransactionGateway => transaction {
+        pub fn create(transaction: transaction::Request) -> transaction::Transaction;
+        pub fn submit_for_settlement(transaction_id: String) -> transaction::Transaction;
+        pub fn submit_for_settlement_with(transaction_id: String, settlement: settlement::Request) -> transaction::Transaction;
+        pub fn submit_for_partial_settlement(transaction_id: String, settlement: settlement::Request) -> transaction::Transaction;
+        pub fn update_details(transaction_id: String, details: settlement::Request) -> transaction::Transaction;
+        pub fn void(transaction_id: String) -> transaction::Transaction;
+        pub fn refund(transaction_id: String) -> transaction::Transaction;
+        pub fn refund_with(transaction_id: String, refund: refund::Request) -> transaction::Transaction;
+        pub fn find(transaction_id: String) -> transaction::Transaction;
+    }
+}
+
+impl TransactionGateway {
+    /// Search for transactions matching the given criteria. Unlike the
+    /// blocking version, this fetches every page of results before resolving.
+    pub fn search(&self, query: transaction::Search) -> ApiCall<Vec<transaction::Transaction>> {
+        spawn(&self.0, move |bt| bt.transaction().search(query)?.collect())
+    }
+}
+
+pooled_gateway! {
+    /// The future-returning counterpart to `::TestingGateway`. Like the blocking
+    /// version, this refuses to run against the Production environment.
+    pub struct TestingGateway => testing {
+        pub fn settle(transaction_id: String) -> transaction::Transaction;
+    }
+}
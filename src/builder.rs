@@ -0,0 +1,217 @@
+//! Configuration of a `Braintree` handle.
+//!
+//! ```rust,no_run
+//! extern crate braintree;
+//!
+//! use braintree::{Braintree, Environment};
+//! use std::time::Duration;
+//!
+//! fn main() {
+//!     let bt = Braintree::builder()
+//!         .environment(Environment::Sandbox)
+//!         .merchant_id("<merchant_id>")
+//!         .public_key("<public_key>")
+//!         .private_key("<private_key>")
+//!         .connect_timeout(Duration::from_secs(5))
+//!         .read_timeout(Duration::from_secs(60))
+//!         .build()
+//!         .expect("failed to create Braintree handle");
+//! }
+//! ```
+
+use error;
+use hyper;
+use hyper_native_tls;
+use retry;
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use transport;
+use {ApiKey, Braintree, Environment, Error};
+
+/// A builder for `Braintree` handles, created with `Braintree::builder()`.
+///
+/// The merchant id, public key and private key are required; everything else
+/// is optional. If no environment is given, `Environment::Sandbox` is used.
+pub struct Builder {
+    env: Environment,
+    merchant_id: Option<String>,
+    public_key: Option<String>,
+    private_key: Option<String>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    proxy: Option<hyper::Url>,
+    pool_size: Option<usize>,
+    user_agent_suffix: Option<String>,
+    retry_policy: retry::RetryPolicy,
+    transport: Option<Box<transport::HttpTransport>>,
+}
+
+impl Builder {
+    pub(crate) fn new() -> Builder {
+        Builder{
+            env: Environment::Sandbox,
+            merchant_id: None,
+            public_key: None,
+            private_key: None,
+            connect_timeout: None,
+            read_timeout: None,
+            write_timeout: None,
+            proxy: None,
+            pool_size: None,
+            user_agent_suffix: None,
+            retry_policy: retry::RetryPolicy::default(),
+            transport: None,
+        }
+    }
+
+    pub fn environment(mut self, env: Environment) -> Builder {
+        self.env = env;
+        self
+    }
+
+    pub fn merchant_id<S: Into<String>>(mut self, merchant_id: S) -> Builder {
+        self.merchant_id = Some(merchant_id.into());
+        self
+    }
+
+    pub fn public_key<S: Into<String>>(mut self, public_key: S) -> Builder {
+        self.public_key = Some(public_key.into());
+        self
+    }
+
+    pub fn private_key<S: Into<String>>(mut self, private_key: S) -> Builder {
+        self.private_key = Some(private_key.into());
+        self
+    }
+
+    /// The maximum amount of time to wait while establishing a connection.
+    /// By default, this is left up to the operating system.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Builder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// The maximum amount of time to wait for data from the gateway. By
+    /// default, reads never time out.
+    pub fn read_timeout(mut self, timeout: Duration) -> Builder {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// The maximum amount of time to wait while sending data to the gateway.
+    /// By default, writes never time out.
+    pub fn write_timeout(mut self, timeout: Duration) -> Builder {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Send requests through the HTTP proxy at the given URL, e.g.
+    /// `http://proxy.internal:3128`. Requests to the gateway are tunneled
+    /// through the proxy using `CONNECT`.
+    pub fn proxy(mut self, url: hyper::Url) -> Builder {
+        self.proxy = Some(url);
+        self
+    }
+
+    /// The maximum number of idle connections to keep open for reuse.
+    pub fn pool_size(mut self, size: usize) -> Builder {
+        self.pool_size = Some(size);
+        self
+    }
+
+    /// Text to append to the `User-Agent` header sent with each request,
+    /// e.g. to identify your application in the gateway's logs.
+    pub fn user_agent_suffix<S: Into<String>>(mut self, suffix: S) -> Builder {
+        self.user_agent_suffix = Some(suffix.into());
+        self
+    }
+
+    /// The policy used to retry requests that fail for transient reasons.
+    pub fn retry_policy(mut self, policy: retry::RetryPolicy) -> Builder {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Send requests using the given transport instead of the default one.
+    /// Note that the timeout, proxy and pool settings only apply to the
+    /// default transport, and are ignored if this is set.
+    pub fn transport<T: transport::HttpTransport + 'static>(mut self, transport: T) -> Builder {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Create the `Braintree` handle. This fails if a required setting is
+    /// missing, or if the default transport can't be initialized.
+    pub fn build(self) -> error::Result<Braintree> {
+        let merchant_id = self.merchant_id.ok_or_else(|| Error::Configuration(String::from("missing merchant id")))?;
+        let public_key = self.public_key.ok_or_else(|| Error::Configuration(String::from("missing public key")))?;
+        let private_key = self.private_key.ok_or_else(|| Error::Configuration(String::from("missing private key")))?;
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => Box::new(hyper_transport(self.connect_timeout, self.read_timeout, self.write_timeout, self.proxy, self.pool_size)?),
+        };
+        let merchant_url = hyper::Url::parse(&format!("{}/merchants/{}/", self.env.base_url(), merchant_id))
+            .map_err(|_| Error::Configuration(format!("invalid merchant id: {}", merchant_id)))?;
+        let user_agent = match self.user_agent_suffix {
+            Some(suffix) => format!("Braintree Rust {} {}", env!("CARGO_PKG_VERSION"), suffix),
+            None => format!("Braintree Rust {}", env!("CARGO_PKG_VERSION")),
+        };
+        Ok(Braintree{
+            creds: Box::new(ApiKey{
+                       env: self.env,
+                       merchant_id: merchant_id,
+                       auth_header: hyper::header::Basic{username: public_key.clone(), password: Some(private_key.clone())},
+                       public_key: public_key,
+                       private_key: private_key,
+                   }),
+            transport: transport,
+            merchant_url: merchant_url,
+            user_agent: user_agent,
+            retry_policy: self.retry_policy,
+        })
+    }
+}
+
+fn hyper_transport(connect_timeout: Option<Duration>, read_timeout: Option<Duration>, write_timeout: Option<Duration>, proxy: Option<hyper::Url>, pool_size: Option<usize>) -> error::Result<transport::HyperTransport> {
+    use hyper::client::pool;
+
+    let ssl = hyper_native_tls::NativeTlsClient::new().map_err(|err| hyper::Error::Ssl(Box::new(err)))?;
+    let connector = move |host: &str, port: u16, _scheme: &str| connect(host, port, connect_timeout);
+    let pool_config = pool::Config{max_idle: pool_size.unwrap_or(pool::Config::default().max_idle)};
+    let mut client = match proxy {
+        Some(url) => {
+            let host = match url.host_str() {
+                Some(host) => String::from(host),
+                None => return Err(Error::Configuration(format!("invalid proxy url: {}", url))),
+            };
+            let port = url.port_or_known_default().unwrap_or(80);
+            let mut config = hyper::client::ProxyConfig::new(url.scheme(), host, port, connector, ssl);
+            config.set_pool_config(Some(pool_config));
+            hyper::Client::with_proxy_config(config)
+        },
+        None => {
+            let https = hyper::net::HttpsConnector::with_connector(ssl, connector);
+            hyper::Client::with_connector(pool::Pool::with_connector(pool_config, https))
+        },
+    };
+    client.set_read_timeout(read_timeout);
+    client.set_write_timeout(write_timeout);
+    Ok(transport::HyperTransport::with_client(client))
+}
+
+fn connect(host: &str, port: u16, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return TcpStream::connect((host, port)),
+    };
+    let mut last_err = None;
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "could not resolve host")))
+}
@@ -1,6 +1,6 @@
 use elementtree;
-use std::convert::From;
-use std::io::Read;
+use error;
+use std::convert::TryFrom;
 use std::fmt::Write;
 use xml;
 
@@ -83,11 +83,12 @@ pub struct ClientToken {
     pub value: String,
 }
 
-impl From<Box<Read>> for ClientToken {
-    fn from(xml: Box<Read>) -> ClientToken {
-        let root = elementtree::Element::from_reader(xml).unwrap();
-        ClientToken{
-            value: String::from(root.find("value").unwrap().text()),
-        }
+impl<'a> TryFrom<&'a elementtree::Element> for ClientToken {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<ClientToken> {
+        Ok(ClientToken{
+            value: ::require_text(root, "value")?,
+        })
     }
 }
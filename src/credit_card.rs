@@ -1,3 +1,6 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
 use std::fmt::Write;
 use xml;
 
@@ -7,15 +10,72 @@ use xml;
 /// credit card data for compliance reasons. Handling credit card data yourself
 /// means that you're subject to [PCI SAQ D
 /// compliance](https://www.pcisecuritystandards.org/pci_security/completing_self_assessment).
+///
+/// Cards returned by the `CreditCardGateway` never include the full card
+/// number or CVV; instead, fields such as `bin`, `last_4` and `masked_number`
+/// are populated so that saved cards can be displayed safely. These
+/// response-only fields are ignored when sending a request.
 #[derive(Debug, Default)]
 pub struct CreditCard {
+    pub billing_address: Option<::address::Address>,
+    pub billing_address_id: Option<String>,
     pub cardholder_name: Option<String>,
+    pub customer_id: Option<String>,
     pub cvv: Option<String>,
     pub expiration_date: Option<String>,
     pub expiration_month: Option<String>,
     pub expiration_year: Option<String>,
     pub number: Option<String>,
+    pub options: Option<Options>,
+    pub payment_method_nonce: Option<String>,
     pub token: Option<String>,
+    /// The first six digits of the card number.
+    pub bin: Option<String>,
+    pub card_type: Option<CardType>,
+    pub created_at: Option<String>,
+    /// Whether this is the customer's default payment method.
+    pub default: Option<bool>,
+    pub expired: Option<bool>,
+    /// A URL pointing to an image of the card brand's logo.
+    pub image_url: Option<String>,
+    pub last_4: Option<String>,
+    pub masked_number: Option<String>,
+    /// A value that uniquely identifies the card number across all of a
+    /// merchant's vaulted cards, useful for detecting duplicates.
+    pub unique_number_identifier: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for CreditCard {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<CreditCard> {
+        Ok(CreditCard{
+            billing_address: ::decode_child(root, "billing-address", ::address::Address::try_from)?,
+            billing_address_id: None,
+            cardholder_name: ::find_text(root, "cardholder-name"),
+            customer_id: ::find_text(root, "customer-id"),
+            cvv: None,
+            expiration_date: ::find_text(root, "expiration-date"),
+            expiration_month: ::find_text(root, "expiration-month"),
+            expiration_year: ::find_text(root, "expiration-year"),
+            // The full card number is never returned by the gateway.
+            number: None,
+            options: None,
+            payment_method_nonce: None,
+            token: ::find_text(root, "token"),
+            bin: ::find_text(root, "bin"),
+            card_type: ::find_text(root, "card-type").map(CardType::from),
+            created_at: ::find_text(root, "created-at"),
+            default: ::find_text(root, "default").map(|s| s == "true"),
+            expired: ::find_text(root, "expired").map(|s| s == "true"),
+            image_url: ::find_text(root, "image-url"),
+            last_4: ::find_text(root, "last-4"),
+            masked_number: ::find_text(root, "masked-number"),
+            unique_number_identifier: ::find_text(root, "unique-number-identifier"),
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
 }
 
 impl ::ToXml for CreditCard {
@@ -23,14 +83,72 @@ impl ::ToXml for CreditCard {
         let name = xml::escape(&name.unwrap_or("credit-card"));
         let mut s = String::new();
         write!(s, "<{}>", name).unwrap();
+        if let Some(ref billing_address) = self.billing_address { write!(s, "{}", billing_address.to_xml(Some("billing-address"))).unwrap(); }
+        write_xml!(s, "billing-address-id", self.billing_address_id);
         write_xml!(s, "cardholder-name", self.cardholder_name);
+        write_xml!(s, "customer-id", self.customer_id);
         write_xml!(s, "cvv", self.cvv);
         write_xml!(s, "expiration-date", self.expiration_date);
         write_xml!(s, "expiration-month", self.expiration_month);
         write_xml!(s, "expiration-year", self.expiration_year);
         write_xml!(s, "number", self.number);
+        if let Some(ref options) = self.options { write!(s, "{}", options.to_xml(None)).unwrap(); }
+        write_xml!(s, "payment-method-nonce", self.payment_method_nonce);
         write_xml!(s, "token", self.token);
         write!(s, "</{}>", name).unwrap();
         s
     }
 }
+
+/// Options that control how a credit card is stored in the vault.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// If this option is passed and the card has already been added to the
+    /// vault, the request will fail.
+    pub fail_on_duplicate_payment_method: Option<bool>,
+    /// Make this card the customer's default payment method.
+    pub make_default: Option<bool>,
+    /// The amount to use when verifying the card. If not specified, the
+    /// gateway's default verification amount will be used.
+    pub verification_amount: Option<String>,
+    /// The merchant account to use when verifying the card.
+    pub verification_merchant_account_id: Option<String>,
+    /// Prompt the gateway to verify the card's AVS and CVV information before
+    /// storing it.
+    pub verify_card: Option<bool>,
+}
+
+impl ::ToXml for Options {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("options"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+        write_xml_type!(s, "fail-on-duplicate-payment-method", "boolean", self.fail_on_duplicate_payment_method);
+        write_xml_type!(s, "make-default", "boolean", self.make_default);
+        write_xml!(s, "verification-amount", self.verification_amount);
+        write_xml!(s, "verification-merchant-account-id", self.verification_merchant_account_id);
+        write_xml_type!(s, "verify-card", "boolean", self.verify_card);
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+string_enum! {
+    /// The brand of a credit card.
+    pub enum CardType {
+        AmericanExpress => "American Express",
+        CarteBlanche => "Carte Blanche",
+        ChinaUnionPay => "China UnionPay",
+        DinersClub => "Diners Club",
+        Discover => "Discover",
+        Elo => "Elo",
+        Jcb => "JCB",
+        Laser => "Laser",
+        Maestro => "Maestro",
+        MasterCard => "MasterCard",
+        Solo => "Solo",
+        Switch => "Switch",
+        UkMaestro => "UK Maestro",
+        Visa => "Visa",
+    }
+}
@@ -0,0 +1,59 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
+
+/// A record containing the result of verifying a credit card.
+///
+/// Verifications are run when storing a card with the `verify_card` option,
+/// and are returned as part of an `ApiErrorResponse` when they fail.
+#[derive(Debug)]
+pub struct CreditCardVerification {
+    pub id: String,
+    pub status: Status,
+    pub amount: Option<String>,
+    pub avs_error_response_code: Option<String>,
+    pub avs_postal_code_response_code: Option<String>,
+    pub avs_street_address_response_code: Option<String>,
+    pub billing: Option<::address::Address>,
+    pub created_at: Option<String>,
+    pub credit_card: Option<::credit_card::CreditCard>,
+    pub currency_iso_code: Option<String>,
+    pub cvv_response_code: Option<String>,
+    pub gateway_rejection_reason: Option<::transaction::GatewayRejectionReason>,
+    pub merchant_account_id: Option<String>,
+    pub processor_response_code: Option<String>,
+    pub processor_response_text: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for CreditCardVerification {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<CreditCardVerification> {
+        Ok(CreditCardVerification{
+            id: ::require_text(root, "id")?,
+            status: Status::from(::require_text(root, "status")?),
+            amount: ::find_text(root, "amount"),
+            avs_error_response_code: ::find_text(root, "avs-error-response-code"),
+            avs_postal_code_response_code: ::find_text(root, "avs-postal-code-response-code"),
+            avs_street_address_response_code: ::find_text(root, "avs-street-address-response-code"),
+            billing: ::decode_child(root, "billing", ::address::Address::try_from)?,
+            created_at: ::find_text(root, "created-at"),
+            credit_card: ::decode_child(root, "credit-card", ::credit_card::CreditCard::try_from)?,
+            currency_iso_code: ::find_text(root, "currency-iso-code"),
+            cvv_response_code: ::find_text(root, "cvv-response-code"),
+            gateway_rejection_reason: ::find_text(root, "gateway-rejection-reason").map(::transaction::GatewayRejectionReason::from),
+            merchant_account_id: ::find_text(root, "merchant-account-id"),
+            processor_response_code: ::find_text(root, "processor-response-code"),
+            processor_response_text: ::find_text(root, "processor-response-text"),
+        })
+    }
+}
+
+string_enum! {
+    pub enum Status {
+        Failed => "failed",
+        GatewayRejected => "gateway_rejected",
+        ProcessorDeclined => "processor_declined",
+        Verified => "verified",
+    }
+}
@@ -1,6 +1,15 @@
+use elementtree;
+use error;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt::Write;
 use xml;
 
+/// A record containing customer details.
+///
+/// When used as part of a `transaction::Request`, only the basic contact
+/// fields are sent; the remaining fields are populated from the vault when a
+/// customer is returned by the `CustomerGateway`.
 #[derive(Debug, Default)]
 pub struct Customer {
     pub company: Option<String>,
@@ -11,6 +20,38 @@ pub struct Customer {
     pub last_name: Option<String>,
     pub phone: Option<String>,
     pub website: Option<String>,
+    /// The addresses stored in the vault for this customer.
+    pub addresses: Vec<::address::Address>,
+    /// The credit cards stored in the vault for this customer.
+    pub credit_cards: Vec<::credit_card::CreditCard>,
+    pub created_at: Option<String>,
+    pub custom_fields: HashMap<String, String>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for Customer {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Customer> {
+        Ok(Customer{
+            company: ::find_text(root, "company"),
+            email: ::find_text(root, "email"),
+            fax: ::find_text(root, "fax"),
+            first_name: ::find_text(root, "first-name"),
+            id: ::find_text(root, "id"),
+            last_name: ::find_text(root, "last-name"),
+            phone: ::find_text(root, "phone"),
+            website: ::find_text(root, "website"),
+            addresses: ::decode_list(root, "addresses", "address", ::address::Address::try_from)?,
+            credit_cards: ::decode_list(root, "credit-cards", "credit-card", ::credit_card::CreditCard::try_from)?,
+            created_at: ::find_text(root, "created-at"),
+            custom_fields: match root.find("custom-fields") {
+                Some(custom_fields) => custom_fields.children().map(|field| (String::from(field.tag().name()), String::from(field.text()))).collect(),
+                None => HashMap::new(),
+            },
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
 }
 
 impl ::ToXml for Customer {
@@ -30,3 +71,141 @@ impl ::ToXml for Customer {
         s
     }
 }
+
+/// A record detailing a request to create or update a customer in the vault.
+///
+/// Like `transaction::Request`, you'll generally want to use the `Default`
+/// trait to fill out only the fields you need:
+///
+/// ```rust
+/// # use braintree::customer;
+/// # let _ =
+/// customer::Request{
+///     first_name: Some(String::from("Jane")),
+///     email: Some(String::from("jane@example.com")),
+///     ..Default::default()
+/// }
+/// # ;
+/// ```
+#[derive(Debug, Default)]
+pub struct Request {
+    pub company: Option<String>,
+    /// A credit card to store as the customer's default payment method.
+    pub credit_card: Option<::credit_card::CreditCard>,
+    pub custom_fields: HashMap<String, String>,
+    pub email: Option<String>,
+    pub fax: Option<String>,
+    pub first_name: Option<String>,
+    /// The id to assign to the new customer. If not specified, the gateway
+    /// will generate one.
+    pub id: Option<String>,
+    pub last_name: Option<String>,
+    /// A payment method nonce to store as the customer's default payment
+    /// method.
+    pub payment_method_nonce: Option<String>,
+    pub phone: Option<String>,
+    pub website: Option<String>,
+}
+
+impl ::ToXml for Request {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("customer"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write_xml!(s, "company", self.company);
+
+        if let Some(ref credit_card) = self.credit_card { write!(s, "{}", credit_card.to_xml(None)).unwrap(); }
+
+        if !self.custom_fields.is_empty() {
+            write!(s, "<custom-fields>").unwrap();
+            for (k, v) in &self.custom_fields {
+                let k = xml::escape(k);
+                let v = xml::escape(v);
+                write!(s, "<{}>{}</{}>", k, v, k).unwrap();
+            }
+            write!(s, "</custom-fields>").unwrap();
+        }
+
+        write_xml!(s, "email", self.email);
+        write_xml!(s, "fax", self.fax);
+        write_xml!(s, "first-name", self.first_name);
+        write_xml!(s, "id", self.id);
+        write_xml!(s, "last-name", self.last_name);
+        write_xml!(s, "payment-method-nonce", self.payment_method_nonce);
+        write_xml!(s, "phone", self.phone);
+        write_xml!(s, "website", self.website);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// A record describing the criteria for a customer search.
+///
+/// Cards can be matched by BIN or last four digits using
+/// `credit_card_number` with `search::Text::StartsWith` or
+/// `search::Text::EndsWith` respectively.
+#[derive(Clone, Debug, Default)]
+pub struct Search {
+    pub address_country_name: Option<::search::Text>,
+    pub address_extended_address: Option<::search::Text>,
+    pub address_first_name: Option<::search::Text>,
+    pub address_last_name: Option<::search::Text>,
+    pub address_locality: Option<::search::Text>,
+    pub address_postal_code: Option<::search::Text>,
+    pub address_region: Option<::search::Text>,
+    pub address_street_address: Option<::search::Text>,
+    pub cardholder_name: Option<::search::Text>,
+    pub company: Option<::search::Text>,
+    pub created_at: Option<::search::Range>,
+    pub credit_card_expiration_date: Option<::search::Text>,
+    pub credit_card_number: Option<::search::Text>,
+    pub email: Option<::search::Text>,
+    pub fax: Option<::search::Text>,
+    pub first_name: Option<::search::Text>,
+    pub id: Option<::search::Text>,
+    pub ids: Vec<String>,
+    pub last_name: Option<::search::Text>,
+    pub payment_method_token: Option<::search::Text>,
+    /// Matches every customer that has a payment method that is a duplicate
+    /// of the one identified by this token.
+    pub payment_method_token_with_duplicates: Option<::search::Text>,
+    pub phone: Option<::search::Text>,
+    pub website: Option<::search::Text>,
+}
+
+impl ::ToXml for Search {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("search"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write_xml_criterion!(s, "address-country-name", self.address_country_name);
+        write_xml_criterion!(s, "address-extended-address", self.address_extended_address);
+        write_xml_criterion!(s, "address-first-name", self.address_first_name);
+        write_xml_criterion!(s, "address-last-name", self.address_last_name);
+        write_xml_criterion!(s, "address-locality", self.address_locality);
+        write_xml_criterion!(s, "address-postal-code", self.address_postal_code);
+        write_xml_criterion!(s, "address-region", self.address_region);
+        write_xml_criterion!(s, "address-street-address", self.address_street_address);
+        write_xml_criterion!(s, "cardholder-name", self.cardholder_name);
+        write_xml_criterion!(s, "company", self.company);
+        write_xml_criterion!(s, "created-at", self.created_at);
+        write_xml_criterion!(s, "credit-card-expiration-date", self.credit_card_expiration_date);
+        write_xml_criterion!(s, "credit-card-number", self.credit_card_number);
+        write_xml_criterion!(s, "email", self.email);
+        write_xml_criterion!(s, "fax", self.fax);
+        write_xml_criterion!(s, "first-name", self.first_name);
+        write_xml_criterion!(s, "id", self.id);
+        write_xml_array!(s, "ids", self.ids);
+        write_xml_criterion!(s, "last-name", self.last_name);
+        write_xml_criterion!(s, "payment-method-token", self.payment_method_token);
+        write_xml_criterion!(s, "payment-method-token-with-duplicates", self.payment_method_token_with_duplicates);
+        write_xml_criterion!(s, "phone", self.phone);
+        write_xml_criterion!(s, "website", self.website);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
@@ -1,3 +1,6 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
 use std::fmt::Write;
 use xml;
 
@@ -14,6 +17,18 @@ pub struct Descriptor {
     pub url: Option<String>,
 }
 
+impl<'a> TryFrom<&'a elementtree::Element> for Descriptor {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Descriptor> {
+        Ok(Descriptor{
+            name: ::find_text(root, "name"),
+            phone: ::find_text(root, "phone"),
+            url: ::find_text(root, "url"),
+        })
+    }
+}
+
 impl ::ToXml for Descriptor {
     fn to_xml(&self, name: Option<&str>) -> String {
         let name = xml::escape(&name.unwrap_or("descriptor"));
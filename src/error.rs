@@ -2,7 +2,8 @@ use elementtree;
 use hyper;
 use std;
 use std::error::Error as StdError;
-use std::convert::From;
+use std::collections::BTreeMap;
+use std::convert::{From, TryFrom};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -18,9 +19,51 @@ pub enum Error {
     /// means that an error occurred with the raw network call, e.g. no
     /// internet access.
     Http(hyper::Error),
+    /// A response body couldn't be decoded, either because it wasn't
+    /// well-formed XML or because an expected element was missing. `element`
+    /// is the path from the root to the element that couldn't be decoded,
+    /// e.g. `subscription/add-ons/add-on/id` (or empty if the body couldn't
+    /// be parsed at all), and `body` is the raw response body.
+    Decode {
+        element: String,
+        body: String,
+    },
     /// A test operation, such as forcing a transaction into a settlement
     /// status, was attempted in a production environment.
     TestOperationInProduction,
+    /// The API keys used to make the request were rejected (HTTP 401).
+    Authentication,
+    /// The API keys used to make the request don't have permission to
+    /// perform the requested operation (HTTP 403).
+    Authorization,
+    /// The requested record, such as a transaction or customer, doesn't
+    /// exist (HTTP 404).
+    NotFound,
+    /// This version of the library is no longer supported by the gateway
+    /// (HTTP 426).
+    UpgradeRequired,
+    /// Too many requests have been made in too short a time (HTTP 429).
+    TooManyRequests,
+    /// The gateway encountered an internal error while processing the
+    /// request (HTTP 500).
+    ServerError,
+    /// The gateway is temporarily unavailable, e.g. for maintenance (HTTP
+    /// 503).
+    ServiceUnavailable,
+    /// The request timed out, either while waiting on the network or within
+    /// the gateway itself.
+    Timeout,
+    /// The client couldn't be created because its configuration was invalid,
+    /// e.g. a required credential was missing.
+    Configuration(String),
+    /// A webhook notification's signature didn't match its payload, or
+    /// wasn't made with this client's API keys.
+    InvalidSignature,
+    /// A webhook challenge wasn't in the format sent by Braintree.
+    InvalidChallenge,
+    /// The task running a `BlockingPoolBraintree` request panicked or was
+    /// cancelled before it completed. The message describes which.
+    TaskFailed(String),
 }
 
 impl std::fmt::Display for Error {
@@ -34,7 +77,20 @@ impl StdError for Error {
         match *self {
             Error::Api(ref response) => &response.message,
             Error::Http(ref err) => err.description(),
+            Error::Decode{..} => "Failed to decode response body",
             Error::TestOperationInProduction => "Operation not allowed in production environment",
+            Error::Authentication => "Authentication failed",
+            Error::Authorization => "Not authorized to perform this operation",
+            Error::NotFound => "Record not found",
+            Error::UpgradeRequired => "Library version is no longer supported",
+            Error::TooManyRequests => "Too many requests",
+            Error::ServerError => "Internal server error",
+            Error::ServiceUnavailable => "Service unavailable",
+            Error::Timeout => "Request timed out",
+            Error::Configuration(ref message) => message,
+            Error::InvalidSignature => "Webhook signature could not be verified",
+            Error::InvalidChallenge => "Webhook challenge is invalid",
+            Error::TaskFailed(ref message) => message,
         }
     }
 
@@ -48,17 +104,26 @@ impl StdError for Error {
 
 impl From<hyper::Error> for Error {
     fn from(error: hyper::Error) -> Error {
+        if let hyper::Error::Io(ref err) = error {
+            match err.kind() {
+                std::io::ErrorKind::TimedOut|std::io::ErrorKind::WouldBlock => return Error::Timeout,
+                _ => (),
+            }
+        }
         Error::Http(error)
     }
 }
 
 impl std::convert::From<Box<std::io::Read>> for Error {
-    fn from(xml: Box<std::io::Read>) -> Error {
-        let root = elementtree::Element::from_reader(xml).unwrap();
-        Error::Api(ApiErrorResponse{
-            message: String::from(root.find("message").unwrap().text()),
-            raw: root,
-        })
+    fn from(mut xml: Box<std::io::Read>) -> Error {
+        let mut body = String::new();
+        if let Err(err) = xml.read_to_string(&mut body) {
+            return Error::from(hyper::Error::from(err));
+        }
+        match ::decode(Box::new(std::io::Cursor::new(body.clone())), |root| ApiErrorResponse::try_from(root)) {
+            Ok(response) => Error::Api(ApiErrorResponse{raw: body, ..response}),
+            Err(err) => err,
+        }
     }
 }
 
@@ -66,6 +131,318 @@ impl std::convert::From<Box<std::io::Read>> for Error {
 pub struct ApiErrorResponse {
     /// The error message from the response body.
     pub message: String,
-    /// The parsed response body returned by the API.
-    pub raw: elementtree::Element,
+    /// The validation errors that caused the request to fail, if any.
+    pub errors: ValidationErrors,
+    /// The transaction that was created by the request, if any. A sale that
+    /// is declined by the processor or rejected by the gateway still creates
+    /// a transaction, which can be inspected for details such as
+    /// `processor_response_code` and `gateway_rejection_reason`.
+    pub transaction: Option<Box<::transaction::Transaction>>,
+    /// The failed credit card verification, if the request included a card
+    /// verification that didn't succeed.
+    pub verification: Option<Box<::credit_card_verification::CreditCardVerification>>,
+    /// The body of the error response, exactly as it was returned by the API.
+    /// This is empty if the response was decoded directly from an `Element`.
+    pub raw: String,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for ApiErrorResponse {
+    type Error = Error;
+
+    fn try_from(root: &'a elementtree::Element) -> Result<ApiErrorResponse> {
+        Ok(ApiErrorResponse{
+            message: ::require_text(root, "message")?,
+            errors: ::decode_child(root, "errors", ValidationErrors::try_from)?.unwrap_or_default(),
+            // These are only included for context, so a record that can't be
+            // decoded shouldn't hide the error itself.
+            transaction: root.find("transaction")
+                .and_then(|transaction| ::transaction::Transaction::try_from(transaction).ok())
+                .map(Box::new),
+            verification: root.find("verification")
+                .and_then(|verification| ::credit_card_verification::CreditCardVerification::try_from(verification).ok())
+                .map(Box::new),
+            raw: String::new(),
+        })
+    }
+}
+
+/// A tree of validation errors, mirroring the structure of the request that
+/// caused them.
+///
+/// Each level of the tree holds the errors for the attributes of one object,
+/// along with the errors for any objects nested within it. For example, an
+/// invalid card number on a transaction request can be found with:
+///
+/// ```rust
+/// # use braintree::error::ApiErrorResponse;
+/// # fn number_errors(response: &ApiErrorResponse) {
+/// let number_errors = response.errors
+///     .for_object("transaction")
+///     .for_object("credit-card")
+///     .on("number");
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+    errors: Vec<ValidationError>,
+    nested: BTreeMap<String, ValidationErrors>,
+}
+
+/// Returned by `ValidationErrors::for_object` for objects without errors.
+static NO_ERRORS: ValidationErrors = ValidationErrors{errors: Vec::new(), nested: BTreeMap::new()};
+
+impl ValidationErrors {
+    /// The errors for a nested object, such as `transaction` or
+    /// `credit-card`. Object names use the same dashed form as the request's
+    /// XML. If the object has no errors, an empty set is returned, so lookups
+    /// can be chained without checking each level.
+    pub fn for_object(&self, name: &str) -> &ValidationErrors {
+        self.nested.get(name).unwrap_or(&NO_ERRORS)
+    }
+
+    /// The errors for a single attribute of this object.
+    pub fn on(&self, attribute: &str) -> Vec<&ValidationError> {
+        self.errors.iter().filter(|error| error.attribute == attribute).collect()
+    }
+
+    /// The errors for this object, excluding those of any nested objects.
+    pub fn shallow_errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    /// Every error for this object and all of its nested objects.
+    pub fn deep_errors(&self) -> Vec<&ValidationError> {
+        let mut errors: Vec<&ValidationError> = self.errors.iter().collect();
+        for nested in self.nested.values() {
+            errors.extend(nested.deep_errors());
+        }
+        errors
+    }
+
+    /// Returns true if neither this object nor any nested object has errors.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.nested.values().all(|nested| nested.is_empty())
+    }
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for ValidationErrors {
+    type Error = Error;
+
+    fn try_from(root: &'a elementtree::Element) -> Result<ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for child in root.children() {
+            match child.tag().name() {
+                "errors" => {
+                    for error in child.find_all("error") {
+                        errors.errors.push(::within(root, ::within(child, ValidationError::try_from(error)))?);
+                    }
+                },
+                name => {
+                    errors.nested.insert(String::from(name), ::within(root, ValidationErrors::try_from(child))?);
+                },
+            }
+        }
+        Ok(errors)
+    }
+}
+
+/// A single validation error on one of a request's attributes.
+#[derive(Debug)]
+pub struct ValidationError {
+    /// The name of the attribute that failed validation, e.g. `number`.
+    pub attribute: String,
+    pub code: ValidationErrorCode,
+    /// A human-readable description of the error.
+    pub message: String,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for ValidationError {
+    type Error = Error;
+
+    fn try_from(root: &'a elementtree::Element) -> Result<ValidationError> {
+        Ok(ValidationError{
+            attribute: ::require_text(root, "attribute")?,
+            code: ValidationErrorCode::from(::require_text(root, "code")?),
+            message: ::require_text(root, "message")?,
+        })
+    }
+}
+
+string_enum! {
+    /// The code identifying a validation error. Codes that aren't listed
+    /// here can still be matched using `ValidationErrorCode::Unknown`; see
+    /// Braintree's [validation error
+    /// reference](https://developers.braintreepayments.com/reference/general/validation-errors/all)
+    /// for the full list.
+    pub enum ValidationErrorCode {
+        AddressCannotBeBlank => "81801",
+        AddressCompanyIsTooLong => "81802",
+        AddressCountryCodeAlpha2IsNotAccepted => "91814",
+        AddressCountryCodeAlpha3IsNotAccepted => "91816",
+        AddressCountryCodeNumericIsNotAccepted => "91817",
+        AddressCountryNameIsNotAccepted => "91803",
+        AddressExtendedAddressIsTooLong => "81804",
+        AddressFirstNameIsTooLong => "81805",
+        AddressInconsistentCountry => "91815",
+        AddressLastNameIsTooLong => "81806",
+        AddressLocalityIsTooLong => "81807",
+        AddressPostalCodeInvalidCharacters => "81813",
+        AddressPostalCodeIsRequired => "81808",
+        AddressPostalCodeIsTooLong => "81809",
+        AddressRegionIsTooLong => "81810",
+        AddressStreetAddressIsRequired => "81811",
+        AddressStreetAddressIsTooLong => "81812",
+        AddressTooManyAddressesPerCustomer => "91818",
+
+        CreditCardCardholderNameIsTooLong => "81723",
+        CreditCardCreditCardTypeIsNotAccepted => "81703",
+        CreditCardCustomerIdIsInvalid => "91705",
+        CreditCardCustomerIdIsRequired => "91704",
+        CreditCardCvvIsInvalid => "81707",
+        CreditCardCvvIsRequired => "81706",
+        CreditCardDuplicateCardExists => "81724",
+        CreditCardExpirationDateConflict => "91708",
+        CreditCardExpirationDateIsInvalid => "81710",
+        CreditCardExpirationDateIsRequired => "81709",
+        CreditCardExpirationDateYearIsInvalid => "81711",
+        CreditCardExpirationMonthIsInvalid => "81712",
+        CreditCardExpirationYearIsInvalid => "81713",
+        CreditCardNumberHasInvalidLength => "81716",
+        CreditCardNumberIsInvalid => "81715",
+        CreditCardNumberIsRequired => "81714",
+        CreditCardNumberMustBeTestNumber => "81717",
+        CreditCardPaymentMethodConflict => "81725",
+        CreditCardTokenInvalid => "91718",
+        CreditCardTokenIsInUse => "91719",
+        CreditCardTokenIsNotAllowed => "91721",
+        CreditCardTokenIsTooLong => "91720",
+
+        CustomerCompanyIsTooLong => "81601",
+        CustomerCustomFieldIsInvalid => "91602",
+        CustomerCustomFieldIsTooLong => "81603",
+        CustomerEmailFormatIsInvalid => "81604",
+        CustomerEmailIsRequired => "81606",
+        CustomerEmailIsTooLong => "81605",
+        CustomerFaxIsTooLong => "81607",
+        CustomerFirstNameIsTooLong => "81608",
+        CustomerIdIsInUse => "91609",
+        CustomerIdIsInvalid => "91610",
+        CustomerIdIsNotAllowed => "91611",
+        CustomerIdIsTooLong => "91612",
+        CustomerLastNameIsTooLong => "81613",
+        CustomerPhoneIsTooLong => "81614",
+        CustomerWebsiteFormatIsInvalid => "81616",
+        CustomerWebsiteIsTooLong => "81615",
+
+        TransactionAmountCannotBeNegative => "81501",
+        TransactionAmountIsInvalid => "81503",
+        TransactionAmountIsRequired => "81502",
+        TransactionAmountIsTooLarge => "81528",
+        TransactionAmountMustBeGreaterThanZero => "81531",
+        TransactionBillingAddressConflict => "91530",
+        TransactionCannotBeVoided => "91504",
+        TransactionCannotRefundCredit => "91505",
+        TransactionCannotRefundUnlessSettled => "91506",
+        TransactionCannotSubmitForSettlement => "91507",
+        TransactionCreditCardIsRequired => "91508",
+        TransactionCustomFieldIsInvalid => "91526",
+        TransactionCustomFieldIsTooLong => "81527",
+        TransactionCustomerDoesNotHaveCreditCard => "91511",
+        TransactionCustomerIdIsInvalid => "91510",
+        TransactionHasAlreadyBeenRefunded => "91512",
+        TransactionMerchantAccountIdIsInvalid => "91513",
+        TransactionMerchantAccountIsSuspended => "91514",
+        TransactionOrderIdIsTooLong => "91501",
+        TransactionPaymentMethodConflict => "91515",
+        TransactionPaymentMethodDoesNotBelongToCustomer => "91516",
+        TransactionPaymentMethodNonceUnknown => "91565",
+        TransactionPaymentMethodTokenCardTypeIsNotAccepted => "91517",
+        TransactionPaymentMethodTokenIsInvalid => "91518",
+        TransactionProcessorAuthorizationCodeCannotBeSet => "91519",
+        TransactionProcessorAuthorizationCodeIsInvalid => "81520",
+        TransactionPurchaseOrderNumberIsTooLong => "91537",
+        TransactionRefundAmountIsTooLarge => "91521",
+        TransactionSettlementAmountIsTooLarge => "91522",
+        TransactionTaxAmountCannotBeNegative => "81534",
+        TransactionTaxAmountFormatIsInvalid => "81535",
+        TransactionTaxAmountIsTooLarge => "81536",
+        TransactionTypeIsInvalid => "91523",
+        TransactionTypeIsRequired => "91524",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const RESPONSE: &str = "<api-error-response>\
+        <errors>\
+            <errors type=\"array\"/>\
+            <transaction>\
+                <errors type=\"array\">\
+                    <error>\
+                        <code>81502</code>\
+                        <attribute type=\"symbol\">amount</attribute>\
+                        <message>Amount is required.</message>\
+                    </error>\
+                </errors>\
+                <credit-card>\
+                    <errors type=\"array\">\
+                        <error>\
+                            <code>81715</code>\
+                            <attribute type=\"symbol\">number</attribute>\
+                            <message>Credit card number is invalid.</message>\
+                        </error>\
+                        <error>\
+                            <code>81707</code>\
+                            <attribute type=\"symbol\">cvv</attribute>\
+                            <message>CVV must be 4 digits for American Express and 3 digits for other card types.</message>\
+                        </error>\
+                    </errors>\
+                </credit-card>\
+            </transaction>\
+        </errors>\
+        <message>Amount is required.\nCredit card number is invalid.</message>\
+    </api-error-response>";
+
+    fn response() -> ApiErrorResponse {
+        match Error::from(Box::new(Cursor::new(RESPONSE)) as Box<std::io::Read>) {
+            Error::Api(response) => response,
+            err => panic!("expected Error::Api, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn decodes_nested_validation_errors() {
+        let response = response();
+        assert_eq!(response.message, "Amount is required.\nCredit card number is invalid.");
+        assert_eq!(response.raw, RESPONSE);
+        assert!(response.errors.shallow_errors().is_empty());
+        assert_eq!(response.errors.deep_errors().len(), 3);
+
+        let transaction = response.errors.for_object("transaction");
+        assert_eq!(transaction.shallow_errors().len(), 1);
+        assert_eq!(transaction.on("amount")[0].code, ValidationErrorCode::TransactionAmountIsRequired);
+        assert_eq!(transaction.deep_errors().len(), 3);
+    }
+
+    #[test]
+    fn chains_lookups_through_nested_objects() {
+        let response = response();
+        let number = response.errors.for_object("transaction").for_object("credit-card").on("number");
+        assert_eq!(number.len(), 1);
+        assert_eq!(number[0].code, ValidationErrorCode::CreditCardNumberIsInvalid);
+        assert_eq!(number[0].message, "Credit card number is invalid.");
+    }
+
+    #[test]
+    fn falls_back_to_no_errors_for_missing_objects() {
+        let response = response();
+        let missing = response.errors.for_object("customer").for_object("credit-card");
+        assert!(missing.is_empty());
+        assert!(missing.on("number").is_empty());
+        assert!(::std::ptr::eq(missing, &NO_ERRORS));
+        assert!(response.errors.for_object("transaction").for_object("billing").is_empty());
+    }
 }
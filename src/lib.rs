@@ -30,12 +30,13 @@
 //! 
 //! fn main() {
 //!     // Create a handle to the Braintree API.
-//!     let bt = Braintree::new(
-//!         Environment::Sandbox,
-//!         "<merchant_id>",
-//!         "<public_key>",
-//!         "<private_key>",
-//!     );
+//!     let bt = Braintree::builder()
+//!         .environment(Environment::Sandbox)
+//!         .merchant_id("<merchant_id>")
+//!         .public_key("<public_key>")
+//!         .private_key("<private_key>")
+//!         .build()
+//!         .expect("failed to create Braintree handle");
 //! 
 //!     // Attempt to charge the provided credit card $10.
 //!     let result = bt.transaction().create(transaction::Request{
@@ -70,10 +71,14 @@
 //! This crate is very much in a pre-alpha state, and as such the design of its
 //! API is subject to change. You have been forewarned!
 
+extern crate base64;
 extern crate elementtree;
+extern crate hmac;
 #[macro_use] extern crate hyper;
 extern crate hyper_native_tls;
 extern crate libflate;
+extern crate sha1;
+#[cfg(feature = "blocking-pool")] extern crate tokio;
 extern crate xml;
 
 macro_rules! write_xml {
@@ -92,61 +97,169 @@ macro_rules! write_xml_type {
     }
 }
 
+macro_rules! write_xml_criterion {
+    ($s:expr, $elem:expr, $value:expr) => {
+        if let Some(ref value) = $value {
+            write!($s, "{}", value.to_xml(Some($elem))).unwrap();
+        }
+    }
+}
+
+macro_rules! write_xml_array {
+    ($s:expr, $elem:expr, $values:expr) => {
+        if !$values.is_empty() {
+            write!($s, "<{} type=\"array\">", $elem).unwrap();
+            for value in &$values {
+                write!($s, "<item>{}</item>", &xml::escape(&String::from(value.clone()))).unwrap();
+            }
+            write!($s, "</{}>", $elem).unwrap();
+        }
+    }
+}
+
+/// Defines an enum for a set of string values returned by the gateway.
+///
+/// Each variant is paired with the string that represents it on the wire,
+/// and an `Unknown(String)` variant is added to capture any value that this
+/// version of the library doesn't know about, so that new values added by
+/// Braintree never cause decoding to fail. `From<String>` and `From<Enum> for
+/// String` are implemented to convert to and from the wire format.
+macro_rules! string_enum {
+    ($(#[$attr:meta])* pub enum $name:ident { $($(#[$vattr:meta])* $variant:ident => $value:expr,)* }) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum $name {
+            $($(#[$vattr])* $variant,)*
+            /// A value that isn't recognized by this version of the library.
+            Unknown(String),
+        }
+
+        impl From<String> for $name {
+            fn from(s: String) -> $name {
+                match s.as_ref() {
+                    $($value => $name::$variant,)*
+                    _ => $name::Unknown(s),
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> String {
+                match value {
+                    $($name::$variant => String::from($value),)*
+                    $name::Unknown(s) => s,
+                }
+            }
+        }
+    }
+}
 
 header! { (XApiVersion, "X-ApiVersion") => [u8] }
 
+use std::convert::TryFrom;
 use std::io::Read;
+pub mod add_on;
 pub mod address;
+#[cfg(feature = "blocking-pool")] pub mod blocking_pool;
+pub mod builder;
 pub mod client_token;
 pub mod credit_card;
+pub mod credit_card_verification;
 pub mod descriptor;
+pub mod discount;
 pub mod customer;
 pub mod error;
+pub mod payment_method;
+pub mod payment_method_nonce;
+pub mod plan;
+pub mod refund;
+pub mod retry;
+pub mod search;
+pub mod settlement;
+pub mod subscription;
 pub mod transaction;
+pub mod transport;
+pub mod webhook_notification;
+pub mod webhook_testing;
 
 pub use address::Address as Address;
+#[cfg(feature = "blocking-pool")] pub use blocking_pool::BlockingPoolBraintree as BlockingPoolBraintree;
 pub use credit_card::CreditCard as CreditCard;
 pub use descriptor::Descriptor as Descriptor;
 pub use customer::Customer as Customer;
 pub use error::Error as Error;
+pub use payment_method::PaymentMethod as PaymentMethod;
 
 pub struct Braintree {
     creds: Box<Credentials>,
-    client: hyper::Client,
+    transport: Box<transport::HttpTransport>,
     merchant_url: hyper::Url,
     user_agent: String,
+    retry_policy: retry::RetryPolicy,
 }
 
 impl Braintree {
+    /// Create a builder for configuring a handle to the Braintree API.
+    pub fn builder() -> builder::Builder {
+        builder::Builder::new()
+    }
+
+    /// Create a handle to the Braintree API using the default transport.
+    ///
+    /// This panics if TLS can't be initialized.
+    #[deprecated(note = "use `Braintree::builder()` instead")]
     pub fn new<S>(env: Environment, merchant_id: S, public_key: S, private_key: S) -> Braintree
         where S: Into<String>
     {
-        let ssl = hyper_native_tls::NativeTlsClient::new().unwrap();
-        let connector = hyper::net::HttpsConnector::new(ssl);
-
-        let merchant_id = merchant_id.into();
-        let public_key = public_key.into();
-        let private_key = private_key.into();
-        // Calculate some things in advance.
-        let merchant_url = hyper::Url::parse(&format!("{}/merchants/{}/", env.base_url(), merchant_id)).unwrap();
-        Braintree{
-            creds: Box::new(ApiKey{
-                       env: env,
-                       merchant_id: merchant_id,
-                       auth_header: hyper::header::Basic{username: public_key.clone(), password: Some(private_key.clone())},
-                       public_key: public_key,
-                       private_key: private_key,
-                   }),
-            client: hyper::Client::with_connector(connector),
-            merchant_url: merchant_url,
-            user_agent: format!("Braintree Rust {}", env!("CARGO_PKG_VERSION")),
-        }
+        Braintree::builder()
+            .environment(env)
+            .merchant_id(merchant_id)
+            .public_key(public_key)
+            .private_key(private_key)
+            .build()
+            .expect("failed to initialize TLS")
+    }
+
+    pub fn add_on(&self) -> AddOnGateway {
+        AddOnGateway(self)
+    }
+
+    pub fn address(&self) -> AddressGateway {
+        AddressGateway(self)
     }
 
     pub fn client_token(&self) -> ClientTokenGateway {
         ClientTokenGateway(self)
     }
 
+    pub fn credit_card(&self) -> CreditCardGateway {
+        CreditCardGateway(self)
+    }
+
+    pub fn customer(&self) -> CustomerGateway {
+        CustomerGateway(self)
+    }
+
+    pub fn discount(&self) -> DiscountGateway {
+        DiscountGateway(self)
+    }
+
+    pub fn payment_method(&self) -> PaymentMethodGateway {
+        PaymentMethodGateway(self)
+    }
+
+    pub fn payment_method_nonce(&self) -> PaymentMethodNonceGateway {
+        PaymentMethodNonceGateway(self)
+    }
+
+    pub fn plan(&self) -> PlanGateway {
+        PlanGateway(self)
+    }
+
+    pub fn subscription(&self) -> SubscriptionGateway {
+        SubscriptionGateway(self)
+    }
+
     pub fn transaction(&self) -> TransactionGateway {
         TransactionGateway(self)
     }
@@ -155,50 +268,104 @@ impl Braintree {
         TestingGateway(self)
     }
 
-    fn execute(&self, method: hyper::method::Method, path: &str, body: Option<&[u8]>) -> hyper::error::Result<hyper::client::response::Response> {
+    pub fn webhook_notification(&self) -> WebhookNotificationGateway {
+        WebhookNotificationGateway(self)
+    }
+
+    pub fn webhook_testing(&self) -> WebhookTestingGateway {
+        WebhookTestingGateway(self)
+    }
+
+    /// Sends a request to the gateway, retrying it according to the retry
+    /// policy. `GET` requests are treated as safe to repeat; anything else is
+    /// only retried if it wasn't sent. See the `retry` module for details.
+    fn execute(&self, method: hyper::method::Method, path: &str, body: Option<&[u8]>) -> hyper::error::Result<transport::Response> {
+        let retry = match method {
+            hyper::method::Method::Get => retry::Retry::Safe,
+            _ => retry::Retry::Unsent,
+        };
+        self.execute_with(method, path, body, retry)
+    }
+
+    fn execute_with(&self, method: hyper::method::Method, path: &str, body: Option<&[u8]>, retry: retry::Retry) -> hyper::error::Result<transport::Response> {
         use hyper::header::{self, Quality, QualityItem};
         use hyper::mime::{Mime, TopLevel, SubLevel};
 
-        let url = self.merchant_url.join(&path).unwrap();
+        let url = self.merchant_url.join(&path)?;
 
-        let mut req = self.client.request(method, url)
-            .header(header::ContentType(Mime(TopLevel::Application, SubLevel::Xml, vec![])))
-            .header(header::Accept(vec![QualityItem::new(Mime(TopLevel::Application, SubLevel::Xml, vec![]), Quality(1000))]))
-            .header(header::AcceptEncoding(vec![QualityItem::new(header::Encoding::Gzip, Quality(1000))]))
-            .header(header::UserAgent(self.user_agent.clone()))
-            .header(header::Authorization(self.creds.authorization_header()))
-            .header(XApiVersion(4));
+        let mut headers = header::Headers::new();
+        headers.set(header::ContentType(Mime(TopLevel::Application, SubLevel::Xml, vec![])));
+        headers.set(header::Accept(vec![QualityItem::new(Mime(TopLevel::Application, SubLevel::Xml, vec![]), Quality(1000))]));
+        headers.set(header::AcceptEncoding(vec![QualityItem::new(header::Encoding::Gzip, Quality(1000))]));
+        headers.set(header::UserAgent(self.user_agent.clone()));
+        headers.set(header::Authorization(self.creds.authorization_header()));
+        headers.set(XApiVersion(4));
 
-        if let Some(data) = body {
-            req = req.body(hyper::client::Body::BufBody(data, data.len()));
+        let mut attempt = 1;
+        loop {
+            let result = self.transport.send(transport::Request{
+                method: method.clone(),
+                url: url.clone(),
+                headers: headers.clone(),
+                body: body,
+            });
+            match self.retry_policy.delay(attempt, retry, &result) {
+                Some(delay) => std::thread::sleep(delay),
+                None => return result,
+            }
+            attempt += 1;
         }
+    }
 
-        req.send()
+    /// Converts an unsuccessful response into an error. Status codes that
+    /// don't carry a Braintree error response, such as 404, are mapped to
+    /// their own variants; anything else is decoded as an API error.
+    fn error(&self, response: transport::Response) -> Error {
+        use hyper::status::StatusCode;
+
+        match response.status {
+            StatusCode::Unauthorized => Error::Authentication,
+            StatusCode::Forbidden => Error::Authorization,
+            StatusCode::NotFound => Error::NotFound,
+            StatusCode::RequestTimeout|StatusCode::GatewayTimeout => Error::Timeout,
+            StatusCode::UpgradeRequired => Error::UpgradeRequired,
+            StatusCode::TooManyRequests => Error::TooManyRequests,
+            StatusCode::InternalServerError => Error::ServerError,
+            StatusCode::ServiceUnavailable => Error::ServiceUnavailable,
+            _ => match self.response_reader(response) {
+                Ok(reader) => Error::from(reader),
+                Err(err) => err,
+            },
+        }
     }
 
     /// Returns a reader that will correctly decode the response body's data based on its Content-Encoding header.
-    fn response_reader(&self, response: hyper::client::response::Response) -> hyper::error::Result<Box<Read>> {
-        // TODO: This is written this way in order to appease the borrow checker, but there's probably a better way to do this.
-        let headers = response.headers.clone();
-        let content_encoding = headers.get::<hyper::header::ContentEncoding>();
-        let mut r: Box<Read> = Box::new(response);
-        // ???: Use Content-Length somehow to provide a hint to the consumer?
-        if let Some(content_encoding) = content_encoding {
-            match content_encoding[0] {
-                hyper::header::Encoding::Gzip => {
-                    r = Box::new(libflate::gzip::Decoder::new(r)?);
-                },
-                _ => panic!("unsupported content encoding: {}", content_encoding[0]),
-            }
-        }
+    /// Only gzip is requested, so any other encoding, e.g. one added by a misbehaving proxy, is reported as an
+    /// `Error::Decode` along with the still-encoded body.
+    fn response_reader(&self, response: transport::Response) -> error::Result<Box<Read>> {
+        use hyper::header::{ContentEncoding, Encoding};
+
+        let encoding = response.headers.get::<ContentEncoding>().and_then(|encodings| encodings.first().cloned());
+        let r: Box<Read> = match encoding {
+            None|Some(Encoding::Identity) => Box::new(std::io::Cursor::new(response.body)),
+            Some(Encoding::Gzip) => Box::new(libflate::gzip::Decoder::new(std::io::Cursor::new(response.body)).map_err(hyper::Error::from)?),
+            Some(_) => return Err(Error::Decode{element: String::new(), body: String::from_utf8_lossy(&response.body).into_owned()}),
+        };
         Ok(r)
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// The Braintree gateway to send requests to.
+#[derive(Clone, Debug, PartialEq)]
 pub enum Environment {
     Sandbox,
     Production,
+    /// A gateway running locally on port 3000, e.g. a stand-in used for
+    /// integration tests.
+    Development,
+    /// A gateway at an arbitrary base URL, such as a QA endpoint. The URL
+    /// should not include the `/merchants` path.
+    Custom(hyper::Url),
 }
 
 impl Environment {
@@ -206,13 +373,27 @@ impl Environment {
         match *self {
             Environment::Sandbox => "https://sandbox.braintreegateway.com",
             Environment::Production => "https://www.braintreegateway.com",
+            Environment::Development => "http://localhost:3000",
+            Environment::Custom(ref url) => url.as_str().trim_end_matches('/'),
+        }
+    }
+
+    /// Whether requests are sent to the production gateway, including via a
+    /// custom URL that points at it.
+    fn is_production(&self) -> bool {
+        match *self {
+            Environment::Production => true,
+            Environment::Custom(ref url) => url.host_str() == Some("www.braintreegateway.com") || url.host_str() == Some("api.braintreegateway.com"),
+            _ => false,
         }
     }
 }
 
-trait Credentials {
-    fn environment(&self) -> Environment;
+trait Credentials: Send + Sync {
+    fn environment(&self) -> &Environment;
     fn merchant_id(&self) -> &str;
+    fn public_key(&self) -> &str;
+    fn private_key(&self) -> &str;
     fn authorization_header(&self) -> hyper::header::Basic;
 }
 
@@ -226,11 +407,70 @@ struct ApiKey {
 }
 
 impl Credentials for ApiKey {
-    fn environment(&self) -> Environment { self.env }
+    fn environment(&self) -> &Environment { &self.env }
     fn merchant_id(&self) -> &str { &self.merchant_id }
+    fn public_key(&self) -> &str { &self.public_key }
+    fn private_key(&self) -> &str { &self.private_key }
     fn authorization_header(&self) -> hyper::header::Basic { self.auth_header.clone() }
 }
 
+pub struct AddOnGateway<'a>(&'a Braintree);
+
+impl<'a> AddOnGateway<'a> {
+    /// List all of the add-ons defined for your account.
+    pub fn all(&self) -> error::Result<Vec<add_on::AddOn>> {
+        let response = self.0.execute(hyper::method::Method::Get, "add_ons", None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => decode(self.0.response_reader(response)?, |root| {
+                within(root, root.find_all("add-on").map(add_on::AddOn::try_from).collect())
+            }),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
+pub struct AddressGateway<'a>(&'a Braintree);
+
+impl<'a> AddressGateway<'a> {
+    /// Store a new address in the vault for an existing customer. A customer
+    /// can have up to 50 addresses.
+    pub fn create(&self, customer_id: String, address: address::Address) -> error::Result<address::Address> {
+        let response = self.0.execute(hyper::method::Method::Post, &format!("customers/{}/addresses", customer_id), Some(address.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created => address::Address::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Retrieve details for one of a customer's addresses.
+    pub fn find(&self, customer_id: String, address_id: String) -> error::Result<address::Address> {
+        let response = self.0.execute(hyper::method::Method::Get, &format!("customers/{}/addresses/{}", customer_id, address_id), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => address::Address::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Update one of a customer's addresses. Only the fields that are set on
+    /// the request will be changed.
+    pub fn update(&self, customer_id: String, address_id: String, address: address::Address) -> error::Result<address::Address> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("customers/{}/addresses/{}", customer_id, address_id), Some(address.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => address::Address::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Delete one of a customer's addresses.
+    pub fn delete(&self, customer_id: String, address_id: String) -> error::Result<()> {
+        let response = self.0.execute(hyper::method::Method::Delete, &format!("customers/{}/addresses/{}", customer_id, address_id), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => Ok(()),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
 pub struct ClientTokenGateway<'a>(&'a Braintree);
 
 impl<'a> ClientTokenGateway<'a> {
@@ -244,12 +484,324 @@ impl<'a> ClientTokenGateway<'a> {
     pub fn generate(&self, req: client_token::Request) -> error::Result<client_token::ClientToken> {
         let response = self.0.execute(hyper::method::Method::Post, "client_token", Some(req.to_xml(None).as_bytes()))?;
         match response.status {
-            hyper::status::StatusCode::Created => Ok(client_token::ClientToken::from(self.0.response_reader(response)?)),
-            _ => Err(Error::from(self.0.response_reader(response)?)),
+            hyper::status::StatusCode::Created => client_token::ClientToken::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
+pub struct CreditCardGateway<'a>(&'a Braintree);
+
+impl<'a> CreditCardGateway<'a> {
+    /// Store a new credit card in the vault. The card must be associated with
+    /// an existing customer by setting `customer_id`.
+    pub fn create(&self, credit_card: credit_card::CreditCard) -> error::Result<credit_card::CreditCard> {
+        let response = self.0.execute(hyper::method::Method::Post, "payment_methods", Some(credit_card.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created => credit_card::CreditCard::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Retrieve details for a vaulted credit card.
+    pub fn find(&self, token: String) -> error::Result<credit_card::CreditCard> {
+        let response = self.0.execute(hyper::method::Method::Get, &format!("payment_methods/credit_card/{}", token), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => credit_card::CreditCard::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Update a vaulted credit card, e.g. to change its expiration date or
+    /// billing address. Only the fields that are set on the request will be
+    /// changed.
+    pub fn update(&self, token: String, credit_card: credit_card::CreditCard) -> error::Result<credit_card::CreditCard> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("payment_methods/credit_card/{}", token), Some(credit_card.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => credit_card::CreditCard::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Delete a credit card from the vault.
+    pub fn delete(&self, token: String) -> error::Result<()> {
+        let response = self.0.execute(hyper::method::Method::Delete, &format!("payment_methods/credit_card/{}", token), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => Ok(()),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
+pub struct CustomerGateway<'a>(&'a Braintree);
+
+impl<'a> CustomerGateway<'a> {
+    /// Create a new customer in the vault. If a credit card or payment
+    /// method nonce is provided, it will be stored as the customer's default
+    /// payment method.
+    pub fn create(&self, customer: customer::Request) -> error::Result<customer::Customer> {
+        let response = self.0.execute(hyper::method::Method::Post, "customers", Some(customer.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created => customer::Customer::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Retrieve details for a customer, including their stored credit cards
+    /// and addresses.
+    pub fn find(&self, customer_id: String) -> error::Result<customer::Customer> {
+        let response = self.0.execute(hyper::method::Method::Get, &format!("customers/{}", customer_id), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => customer::Customer::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Update an existing customer. Only the fields that are set on the
+    /// request will be changed.
+    pub fn update(&self, customer_id: String, customer: customer::Request) -> error::Result<customer::Customer> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("customers/{}", customer_id), Some(customer.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => customer::Customer::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Search for customers matching the given criteria. The matching
+    /// customers are fetched lazily, in pages, as the returned iterator is
+    /// consumed.
+    pub fn search(&self, query: customer::Search) -> error::Result<search::Results<'a, customer::Customer>> {
+        let criteria = query.to_xml(None);
+        search::Results::new(self.0, "customers", "customer", criteria, move |ids| {
+            let mut page = query.clone();
+            page.ids = ids.to_vec();
+            page.to_xml(None)
+        })
+    }
+
+    /// Delete a customer, along with all of their stored payment methods and
+    /// addresses.
+    pub fn delete(&self, customer_id: String) -> error::Result<()> {
+        let response = self.0.execute(hyper::method::Method::Delete, &format!("customers/{}", customer_id), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => Ok(()),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
+pub struct DiscountGateway<'a>(&'a Braintree);
+
+impl<'a> DiscountGateway<'a> {
+    /// List all of the discounts defined for your account.
+    pub fn all(&self) -> error::Result<Vec<discount::Discount>> {
+        let response = self.0.execute(hyper::method::Method::Get, "discounts", None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => decode(self.0.response_reader(response)?, |root| {
+                within(root, root.find_all("discount").map(discount::Discount::try_from).collect())
+            }),
+            _ => Err(self.0.error(response)),
         }
     }
 }
 
+pub struct PaymentMethodGateway<'a>(&'a Braintree);
+
+impl<'a> PaymentMethodGateway<'a> {
+    /// Store a payment method in the vault for an existing customer. The
+    /// type of payment method that gets created depends on the nonce that was
+    /// provided.
+    pub fn create(&self, payment_method: payment_method::Request) -> error::Result<payment_method::PaymentMethod> {
+        let response = self.0.execute(hyper::method::Method::Post, "payment_methods", Some(payment_method.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created => payment_method::PaymentMethod::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Retrieve details for a vaulted payment method of any type.
+    pub fn find(&self, token: String) -> error::Result<payment_method::PaymentMethod> {
+        let response = self.0.execute(hyper::method::Method::Get, &format!("payment_methods/any/{}", token), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => payment_method::PaymentMethod::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Update a vaulted payment method. Only the fields that are set on the
+    /// request will be changed.
+    pub fn update(&self, token: String, payment_method: payment_method::Request) -> error::Result<payment_method::PaymentMethod> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("payment_methods/any/{}", token), Some(payment_method.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => payment_method::PaymentMethod::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Delete a payment method from the vault.
+    pub fn delete(&self, token: String) -> error::Result<()> {
+        let response = self.0.execute(hyper::method::Method::Delete, &format!("payment_methods/any/{}", token), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => Ok(()),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Grant another merchant access to one of your vaulted payment methods,
+    /// returning a nonce that the receiving merchant can use to create
+    /// transactions.
+    pub fn grant(&self, grant: payment_method::GrantRequest) -> error::Result<payment_method_nonce::PaymentMethodNonce> {
+        let response = self.0.execute(hyper::method::Method::Post, "payment_methods/grant", Some(grant.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created|hyper::status::StatusCode::Ok => payment_method_nonce::PaymentMethodNonce::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Revoke a previously-granted payment method, preventing the receiving
+    /// merchant from using it any further.
+    pub fn revoke(&self, token: String) -> error::Result<()> {
+        // Revoking takes the same payload as granting, minus the options.
+        let revoke = payment_method::GrantRequest{shared_payment_method_token: token, ..Default::default()};
+        let response = self.0.execute(hyper::method::Method::Post, "payment_methods/revoke", Some(revoke.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => Ok(()),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
+pub struct PaymentMethodNonceGateway<'a>(&'a Braintree);
+
+impl<'a> PaymentMethodNonceGateway<'a> {
+    /// Create a one-time-use nonce from a vaulted payment method.
+    pub fn create(&self, payment_method_token: String) -> error::Result<payment_method_nonce::PaymentMethodNonce> {
+        let response = self.0.execute(hyper::method::Method::Post, &format!("payment_methods/{}/nonces", payment_method_token), None)?;
+        match response.status {
+            hyper::status::StatusCode::Created => payment_method_nonce::PaymentMethodNonce::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Retrieve details for a nonce, such as its type, BIN data and 3D Secure
+    /// information. This is useful for inspecting a nonce before creating a
+    /// transaction with it.
+    pub fn find(&self, nonce: String) -> error::Result<payment_method_nonce::PaymentMethodNonce> {
+        let response = self.0.execute(hyper::method::Method::Get, &format!("payment_method_nonces/{}", nonce), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => payment_method_nonce::PaymentMethodNonce::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
+pub struct PlanGateway<'a>(&'a Braintree);
+
+impl<'a> PlanGateway<'a> {
+    /// List all of the plans defined for your account, along with their
+    /// add-ons and discounts.
+    pub fn all(&self) -> error::Result<Vec<plan::Plan>> {
+        let response = self.0.execute(hyper::method::Method::Get, "plans", None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => decode(self.0.response_reader(response)?, |root| {
+                within(root, root.find_all("plan").map(plan::Plan::try_from).collect())
+            }),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Create a new plan.
+    pub fn create(&self, plan: plan::Request) -> error::Result<plan::Plan> {
+        let response = self.0.execute(hyper::method::Method::Post, "plans", Some(plan.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created => plan::Plan::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Look up a plan by its id.
+    pub fn find(&self, plan_id: String) -> error::Result<plan::Plan> {
+        let response = self.0.execute(hyper::method::Method::Get, &format!("plans/{}", plan_id), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => plan::Plan::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Update an existing plan. Changes don't affect existing subscriptions
+    /// to the plan.
+    pub fn update(&self, plan_id: String, plan: plan::Request) -> error::Result<plan::Plan> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("plans/{}", plan_id), Some(plan.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => plan::Plan::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+}
+
+pub struct SubscriptionGateway<'a>(&'a Braintree);
+
+impl<'a> SubscriptionGateway<'a> {
+    /// Create a new subscription.
+    pub fn create(&self, subscription: subscription::Request) -> error::Result<subscription::Subscription> {
+        let response = self.0.execute(hyper::method::Method::Post, "subscriptions", Some(subscription.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created => subscription::Subscription::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Look up a subscription by its id.
+    pub fn find(&self, subscription_id: String) -> error::Result<subscription::Subscription> {
+        let response = self.0.execute(hyper::method::Method::Get, &format!("subscriptions/{}", subscription_id), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => subscription::Subscription::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Update an existing subscription, e.g. to change its price, plan,
+    /// payment method, add-ons or discounts.
+    pub fn update(&self, subscription_id: String, subscription: subscription::Request) -> error::Result<subscription::Subscription> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("subscriptions/{}", subscription_id), Some(subscription.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => subscription::Subscription::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Cancel a subscription. Canceled subscriptions can't be reactivated.
+    pub fn cancel(&self, subscription_id: String) -> error::Result<subscription::Subscription> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("subscriptions/{}/cancel", subscription_id), None)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => subscription::Subscription::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Retry charging a past-due subscription, returning the resulting
+    /// transaction.
+    pub fn retry_charge(&self, charge: subscription::RetryCharge) -> error::Result<transaction::Transaction> {
+        let response = self.0.execute(hyper::method::Method::Post, "transactions", Some(charge.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Search for subscriptions matching the given criteria. The matching
+    /// subscriptions are fetched lazily, in pages, as the returned iterator
+    /// is consumed.
+    pub fn search(&self, query: subscription::Search) -> error::Result<search::Results<'a, subscription::Subscription>> {
+        let criteria = query.to_xml(None);
+        search::Results::new(self.0, "subscriptions", "subscription", criteria, move |ids| {
+            let mut page = query.clone();
+            page.ids = ids.to_vec();
+            page.to_xml(None)
+        })
+    }
+}
+
 pub struct TransactionGateway<'a>(&'a Braintree);
 
 impl<'a> TransactionGateway<'a> {
@@ -270,17 +822,46 @@ impl<'a> TransactionGateway<'a> {
     pub fn create(&self, transaction: transaction::Request) -> error::Result<transaction::Transaction> {
         let response = self.0.execute(hyper::method::Method::Post, "transactions", Some(transaction.to_xml(None).as_bytes()))?;
         match response.status {
-            hyper::status::StatusCode::Created => Ok(transaction::Transaction::from(self.0.response_reader(response)?)),
-            _ => Err(Error::from(self.0.response_reader(response)?)),
+            hyper::status::StatusCode::Created => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
         }
     }
 
     /// Submit an authorized transaction for settlement.
     pub fn submit_for_settlement(&self, transaction_id: String) -> error::Result<transaction::Transaction> {
-        let response = self.0.execute(hyper::method::Method::Put, &format!("transactions/{}/submit_for_settlement", transaction_id), None)?;
+        self.submit_for_settlement_with(transaction_id, Default::default())
+    }
+
+    /// Submit an authorized transaction for settlement with additional
+    /// details, such as an amount smaller than the authorized amount, or an
+    /// order id and descriptor that are only known at settlement time.
+    pub fn submit_for_settlement_with(&self, transaction_id: String, settlement: settlement::Request) -> error::Result<transaction::Transaction> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("transactions/{}/submit_for_settlement", transaction_id), Some(settlement.to_xml(None).as_bytes()))?;
         match response.status {
-            hyper::status::StatusCode::Ok => Ok(transaction::Transaction::from(self.0.response_reader(response)?)),
-            _ => Err(Error::from(self.0.response_reader(response)?)),
+            hyper::status::StatusCode::Ok => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Settle part of an authorized transaction, e.g. when an order is split
+    /// across multiple shipments. Each call creates and returns a new
+    /// transaction for the settled amount; the original authorization may be
+    /// partially settled multiple times, up to its authorized amount.
+    pub fn submit_for_partial_settlement(&self, transaction_id: String, settlement: settlement::Request) -> error::Result<transaction::Transaction> {
+        let response = self.0.execute(hyper::method::Method::Post, &format!("transactions/{}/submit_for_partial_settlement", transaction_id), Some(settlement.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Created|hyper::status::StatusCode::Ok => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
+        }
+    }
+
+    /// Update the amount, order id or descriptor of a transaction that has
+    /// already been submitted for settlement, but has not yet settled.
+    pub fn update_details(&self, transaction_id: String, details: settlement::Request) -> error::Result<transaction::Transaction> {
+        let response = self.0.execute(hyper::method::Method::Put, &format!("transactions/{}/update_details", transaction_id), Some(details.to_xml(None).as_bytes()))?;
+        match response.status {
+            hyper::status::StatusCode::Ok => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
         }
     }
 
@@ -290,8 +871,8 @@ impl<'a> TransactionGateway<'a> {
     pub fn void(&self, transaction_id: String) -> error::Result<transaction::Transaction> {
         let response = self.0.execute(hyper::method::Method::Put, &format!("transactions/{}/void", transaction_id), None)?;
         match response.status {
-            hyper::status::StatusCode::Ok => Ok(transaction::Transaction::from(self.0.response_reader(response)?)),
-            _ => Err(Error::from(self.0.response_reader(response)?)),
+            hyper::status::StatusCode::Ok => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
         }
     }
 
@@ -299,11 +880,16 @@ impl<'a> TransactionGateway<'a> {
     /// new credit transaction. You must pass a settled or settling
     /// `transaction_id` in order to execute a valid refund.
     pub fn refund(&self, transaction_id: String) -> error::Result<transaction::Transaction> {
-        // TODO: add an optional amount to refund the transaction partially
-        let response = self.0.execute(hyper::method::Method::Post, &format!("transactions/{}/refund", transaction_id), None)?;
+        self.refund_with(transaction_id, Default::default())
+    }
+
+    /// Refund a transaction with additional options, such as an amount
+    /// smaller than the settled amount in order to issue a partial refund.
+    pub fn refund_with(&self, transaction_id: String, refund: refund::Request) -> error::Result<transaction::Transaction> {
+        let response = self.0.execute(hyper::method::Method::Post, &format!("transactions/{}/refund", transaction_id), Some(refund.to_xml(None).as_bytes()))?;
         match response.status {
-            hyper::status::StatusCode::Created|hyper::status::StatusCode::Ok => Ok(transaction::Transaction::from(self.0.response_reader(response)?)),
-            _ => Err(Error::from(self.0.response_reader(response)?)),
+            hyper::status::StatusCode::Created|hyper::status::StatusCode::Ok => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
         }
     }
 
@@ -311,33 +897,172 @@ impl<'a> TransactionGateway<'a> {
     pub fn find(&self, transaction_id: String) -> error::Result<transaction::Transaction> {
         let response = self.0.execute(hyper::method::Method::Get, &format!("transactions/{}", transaction_id), None)?;
         match response.status {
-            hyper::status::StatusCode::Ok => Ok(transaction::Transaction::from(self.0.response_reader(response)?)),
-            _ => Err(Error::from(self.0.response_reader(response)?)),
+            hyper::status::StatusCode::Ok => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
         }
     }
+
+    /// Search for transactions matching the given criteria. The matching
+    /// transactions are fetched lazily, in pages, as the returned iterator is
+    /// consumed.
+    pub fn search(&self, query: transaction::Search) -> error::Result<search::Results<'a, transaction::Transaction>> {
+        let criteria = query.to_xml(None);
+        search::Results::new(self.0, "transactions", "transaction", criteria, move |ids| {
+            let mut page = query.clone();
+            page.ids = ids.to_vec();
+            page.to_xml(None)
+        })
+    }
 }
 
 pub struct TestingGateway<'a>(&'a Braintree);
 
 impl<'a> TestingGateway<'a> {
     fn set_status(&self, transaction_id: String, status: String) -> error::Result<transaction::Transaction> {
-        if self.0.creds.environment() == Environment::Production {
+        if self.0.creds.environment().is_production() {
             return Err(Error::TestOperationInProduction);
         }
         let response = self.0.execute(hyper::method::Method::Put, &format!("transactions/{}/{}", transaction_id, status), None)?;
         match response.status {
-            hyper::status::StatusCode::Ok => Ok(transaction::Transaction::from(self.0.response_reader(response)?)),
-            _ => Err(Error::from(self.0.response_reader(response)?)),
+            hyper::status::StatusCode::Ok => transaction::Transaction::from_xml(self.0.response_reader(response)?),
+            _ => Err(self.0.error(response)),
         }
     }
 
     /// Force a transaction into a settled state. Note that this is intended
-    /// for testing, and will only work in the Sandbox environment.
+    /// for testing, and will fail with `Error::TestOperationInProduction` in
+    /// the Production environment.
     pub fn settle(&self, transaction_id: String) -> error::Result<transaction::Transaction> {
         self.set_status(transaction_id, String::from("settle"))
     }
 }
 
+pub struct WebhookNotificationGateway<'a>(&'a Braintree);
+
+impl<'a> WebhookNotificationGateway<'a> {
+    /// Parse a webhook notification from the `bt_signature` and
+    /// `bt_payload` fields posted to your endpoint. This fails with
+    /// `Error::InvalidSignature` if the signature doesn't match the payload
+    /// or wasn't made with this handle's API keys.
+    pub fn parse(&self, bt_signature: &str, bt_payload: &str) -> error::Result<webhook_notification::WebhookNotification> {
+        webhook_notification::parse(self.0.creds.public_key(), self.0.creds.private_key(), bt_signature, bt_payload)
+    }
+
+    /// Answer the `bt_challenge` sent by Braintree when registering a webhook
+    /// endpoint. The returned string should be sent back as the response
+    /// body.
+    pub fn verify(&self, bt_challenge: &str) -> error::Result<String> {
+        webhook_notification::verify(self.0.creds.public_key(), self.0.creds.private_key(), bt_challenge)
+    }
+}
+
+pub struct WebhookTestingGateway<'a>(&'a Braintree);
+
+impl<'a> WebhookTestingGateway<'a> {
+    /// Generate a signed `(bt_signature, bt_payload)` pair for a sample
+    /// notification of the given kind, whose subject has the given id. Like
+    /// `TestingGateway`, this will fail with `Error::TestOperationInProduction`
+    /// in the Production environment.
+    pub fn sample_notification(&self, kind: webhook_notification::Kind, id: &str) -> error::Result<(String, String)> {
+        if self.0.creds.environment().is_production() {
+            return Err(Error::TestOperationInProduction);
+        }
+        Ok(webhook_testing::sample_notification(self.0.creds.public_key(), self.0.creds.private_key(), kind, id))
+    }
+}
+
 trait ToXml {
     fn to_xml(&self, name: Option<&str>) -> String;
 }
+
+/// Decoding of a response body into one of the crate's record types.
+///
+/// Records implement `TryFrom<&elementtree::Element>`, which fails with an
+/// `Error::Decode` describing the element that couldn't be decoded; this
+/// fills in that error with the raw response body so that it can be logged.
+trait FromXml: Sized {
+    fn from_xml(xml: Box<Read>) -> error::Result<Self>;
+}
+
+impl<T> FromXml for T where T: for<'a> TryFrom<&'a elementtree::Element, Error=Error> {
+    fn from_xml(xml: Box<Read>) -> error::Result<T> {
+        decode(xml, |root| T::try_from(root))
+    }
+}
+
+/// Decodes a response body using the provided function, filling in any
+/// decode error it returns with the raw response body.
+fn decode<T, F>(mut xml: Box<Read>, f: F) -> error::Result<T>
+    where F: FnOnce(&elementtree::Element) -> error::Result<T>
+{
+    let mut body = String::new();
+    xml.read_to_string(&mut body).map_err(hyper::Error::from)?;
+    let root = match elementtree::Element::from_reader(body.as_bytes()) {
+        Ok(root) => root,
+        Err(_) => return Err(Error::Decode{element: String::new(), body: body}),
+    };
+    f(&root).map_err(|err| match err {
+        Error::Decode{element, ..} => Error::Decode{element: element, body: body},
+        err => err,
+    })
+}
+
+/// Prefixes the path in a decode error with the name of `parent`. Decoders
+/// only know the names of their own element and its children, so each level
+/// of nesting adds its own name as the error is passed up, giving the full
+/// path from the root, e.g. `transaction/credit-card/token`.
+fn within<T>(parent: &elementtree::Element, result: error::Result<T>) -> error::Result<T> {
+    result.map_err(|err| match err {
+        Error::Decode{element, body} => Error::Decode{element: format!("{}/{}", parent.tag().name(), element), body: body},
+        err => err,
+    })
+}
+
+/// Decodes the named child element using the provided function, or returns
+/// `None` if it is missing.
+fn decode_child<'a, T, F>(root: &'a elementtree::Element, name: &'a str, f: F) -> error::Result<Option<T>>
+    where F: FnOnce(&'a elementtree::Element) -> error::Result<T>
+{
+    root.find(name).map(|child| within(root, f(child))).transpose()
+}
+
+/// Decodes each `item` element within the named list element using the
+/// provided function, or returns an empty list if it is missing.
+fn decode_list<'a, T, F>(root: &'a elementtree::Element, name: &'a str, item: &'a str, f: F) -> error::Result<Vec<T>>
+    where F: FnMut(&'a elementtree::Element) -> error::Result<T>
+{
+    match root.find(name) {
+        Some(list) => within(root, within(list, list.find_all(item).map(f).collect())),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Returns the text of the named child element, or a decode error if it is
+/// missing.
+fn require_text(root: &elementtree::Element, name: &str) -> error::Result<String> {
+    match root.find(name) {
+        Some(elem) => Ok(String::from(elem.text())),
+        None => Err(Error::Decode{element: format!("{}/{}", root.tag().name(), name), body: String::new()}),
+    }
+}
+
+/// Returns the text of the named child element, or `None` if it is either
+/// missing or marked as nil.
+fn find_text(root: &elementtree::Element, name: &str) -> Option<String> {
+    match root.find(name) {
+        Some(elem) if elem.get_attr("nil") != Some("true") => Some(String::from(elem.text())),
+        _ => None,
+    }
+}
+
+/// Parses the text of the named child element, returning `None` if it is
+/// either missing or marked as nil, or a decode error if it can't be parsed.
+fn parse_text<T: std::str::FromStr>(root: &elementtree::Element, name: &str) -> error::Result<Option<T>> {
+    match find_text(root, name) {
+        Some(text) => match text.trim().parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(Error::Decode{element: format!("{}/{}", root.tag().name(), name), body: String::new()}),
+        },
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,332 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use xml;
+
+/// A payment method stored in the vault.
+///
+/// The gateway returns a different record depending on the type of payment
+/// method, which is determined by the name of the response's root element.
+/// Types that aren't otherwise supported by this library are returned as
+/// `Unknown`, with their raw XML.
+#[derive(Debug)]
+pub enum PaymentMethod {
+    ApplePayCard(ApplePayCard),
+    CreditCard(Box<::credit_card::CreditCard>),
+    GooglePayCard(GooglePayCard),
+    PayPalAccount(PayPalAccount),
+    UsBankAccount(UsBankAccount),
+    VenmoAccount(VenmoAccount),
+    Unknown {
+        /// The name of the record's root element, e.g. `visa-checkout-card`.
+        name: String,
+        token: Option<String>,
+        /// The record's XML.
+        xml: String,
+    },
+}
+
+impl PaymentMethod {
+    /// The token identifying this payment method in the vault.
+    pub fn token(&self) -> Option<&str> {
+        match *self {
+            PaymentMethod::ApplePayCard(ref card) => Some(&card.token),
+            PaymentMethod::CreditCard(ref card) => card.token.as_deref(),
+            PaymentMethod::GooglePayCard(ref card) => Some(&card.token),
+            PaymentMethod::PayPalAccount(ref account) => Some(&account.token),
+            PaymentMethod::UsBankAccount(ref account) => Some(&account.token),
+            PaymentMethod::VenmoAccount(ref account) => Some(&account.token),
+            PaymentMethod::Unknown{ref token, ..} => token.as_deref(),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for PaymentMethod {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<PaymentMethod> {
+        Ok(match root.tag().name() {
+            "apple-pay-card" => PaymentMethod::ApplePayCard(ApplePayCard::try_from(root)?),
+            "credit-card" => PaymentMethod::CreditCard(Box::new(::credit_card::CreditCard::try_from(root)?)),
+            "android-pay-card" => PaymentMethod::GooglePayCard(GooglePayCard::try_from(root)?),
+            "paypal-account" => PaymentMethod::PayPalAccount(PayPalAccount::try_from(root)?),
+            "us-bank-account" => PaymentMethod::UsBankAccount(UsBankAccount::try_from(root)?),
+            "venmo-account" => PaymentMethod::VenmoAccount(VenmoAccount::try_from(root)?),
+            name => PaymentMethod::Unknown{
+                name: String::from(name),
+                token: ::find_text(root, "token"),
+                xml: root.to_string().unwrap_or_default(),
+            },
+        })
+    }
+}
+
+/// An Apple Pay card stored in the vault.
+#[derive(Debug)]
+pub struct ApplePayCard {
+    pub token: String,
+    pub bin: Option<String>,
+    pub card_type: Option<::credit_card::CardType>,
+    pub cardholder_name: Option<String>,
+    pub created_at: Option<String>,
+    pub customer_id: Option<String>,
+    pub default: bool,
+    pub expiration_month: Option<String>,
+    pub expiration_year: Option<String>,
+    pub expired: Option<bool>,
+    pub image_url: Option<String>,
+    pub last_4: Option<String>,
+    pub payment_instrument_name: Option<String>,
+    pub source_description: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for ApplePayCard {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<ApplePayCard> {
+        Ok(ApplePayCard{
+            token: ::require_text(root, "token")?,
+            bin: ::find_text(root, "bin"),
+            card_type: ::find_text(root, "card-type").map(::credit_card::CardType::from),
+            cardholder_name: ::find_text(root, "cardholder-name"),
+            created_at: ::find_text(root, "created-at"),
+            customer_id: ::find_text(root, "customer-id"),
+            default: ::require_text(root, "default")? == "true",
+            expiration_month: ::find_text(root, "expiration-month"),
+            expiration_year: ::find_text(root, "expiration-year"),
+            expired: ::find_text(root, "expired").map(|s| s == "true"),
+            image_url: ::find_text(root, "image-url"),
+            last_4: ::find_text(root, "last-4"),
+            payment_instrument_name: ::find_text(root, "payment-instrument-name"),
+            source_description: ::find_text(root, "source-description"),
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
+}
+
+/// A Google Pay card stored in the vault. The gateway still refers to these
+/// as Android Pay cards.
+#[derive(Debug)]
+pub struct GooglePayCard {
+    pub token: String,
+    pub bin: Option<String>,
+    pub created_at: Option<String>,
+    pub customer_id: Option<String>,
+    pub default: bool,
+    pub expiration_month: Option<String>,
+    pub expiration_year: Option<String>,
+    pub google_transaction_id: Option<String>,
+    pub image_url: Option<String>,
+    pub is_network_tokenized: Option<bool>,
+    pub source_card_last_4: Option<String>,
+    pub source_card_type: Option<String>,
+    pub source_description: Option<String>,
+    pub updated_at: Option<String>,
+    pub virtual_card_last_4: Option<String>,
+    pub virtual_card_type: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for GooglePayCard {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<GooglePayCard> {
+        Ok(GooglePayCard{
+            token: ::require_text(root, "token")?,
+            bin: ::find_text(root, "bin"),
+            created_at: ::find_text(root, "created-at"),
+            customer_id: ::find_text(root, "customer-id"),
+            default: ::require_text(root, "default")? == "true",
+            expiration_month: ::find_text(root, "expiration-month"),
+            expiration_year: ::find_text(root, "expiration-year"),
+            google_transaction_id: ::find_text(root, "google-transaction-id"),
+            image_url: ::find_text(root, "image-url"),
+            is_network_tokenized: ::find_text(root, "is-network-tokenized").map(|s| s == "true"),
+            source_card_last_4: ::find_text(root, "source-card-last-4"),
+            source_card_type: ::find_text(root, "source-card-type"),
+            source_description: ::find_text(root, "source-description"),
+            updated_at: ::find_text(root, "updated-at"),
+            virtual_card_last_4: ::find_text(root, "virtual-card-last-4"),
+            virtual_card_type: ::find_text(root, "virtual-card-type"),
+        })
+    }
+}
+
+/// A PayPal account stored in the vault.
+#[derive(Debug)]
+pub struct PayPalAccount {
+    pub token: String,
+    pub billing_agreement_id: Option<String>,
+    pub created_at: Option<String>,
+    pub customer_id: Option<String>,
+    pub default: bool,
+    pub email: Option<String>,
+    pub image_url: Option<String>,
+    pub payer_id: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for PayPalAccount {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<PayPalAccount> {
+        Ok(PayPalAccount{
+            token: ::require_text(root, "token")?,
+            billing_agreement_id: ::find_text(root, "billing-agreement-id"),
+            created_at: ::find_text(root, "created-at"),
+            customer_id: ::find_text(root, "customer-id"),
+            default: ::require_text(root, "default")? == "true",
+            email: ::find_text(root, "email"),
+            image_url: ::find_text(root, "image-url"),
+            payer_id: ::find_text(root, "payer-id"),
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
+}
+
+/// A US bank account stored in the vault, used for ACH payments.
+#[derive(Debug)]
+pub struct UsBankAccount {
+    pub token: String,
+    pub account_holder_name: Option<String>,
+    pub account_type: Option<String>,
+    pub bank_name: Option<String>,
+    pub created_at: Option<String>,
+    pub customer_id: Option<String>,
+    pub default: bool,
+    pub image_url: Option<String>,
+    pub last_4: Option<String>,
+    pub ownership_type: Option<String>,
+    pub routing_number: Option<String>,
+    pub updated_at: Option<String>,
+    pub verified: Option<bool>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for UsBankAccount {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<UsBankAccount> {
+        Ok(UsBankAccount{
+            token: ::require_text(root, "token")?,
+            account_holder_name: ::find_text(root, "account-holder-name"),
+            account_type: ::find_text(root, "account-type"),
+            bank_name: ::find_text(root, "bank-name"),
+            created_at: ::find_text(root, "created-at"),
+            customer_id: ::find_text(root, "customer-id"),
+            default: ::require_text(root, "default")? == "true",
+            image_url: ::find_text(root, "image-url"),
+            last_4: ::find_text(root, "last-4"),
+            ownership_type: ::find_text(root, "ownership-type"),
+            routing_number: ::find_text(root, "routing-number"),
+            updated_at: ::find_text(root, "updated-at"),
+            verified: ::find_text(root, "verified").map(|s| s == "true"),
+        })
+    }
+}
+
+/// A Venmo account stored in the vault.
+#[derive(Debug)]
+pub struct VenmoAccount {
+    pub token: String,
+    pub created_at: Option<String>,
+    pub customer_id: Option<String>,
+    pub default: bool,
+    pub image_url: Option<String>,
+    pub source_description: Option<String>,
+    pub updated_at: Option<String>,
+    pub username: Option<String>,
+    pub venmo_user_id: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for VenmoAccount {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<VenmoAccount> {
+        Ok(VenmoAccount{
+            token: ::require_text(root, "token")?,
+            created_at: ::find_text(root, "created-at"),
+            customer_id: ::find_text(root, "customer-id"),
+            default: ::require_text(root, "default")? == "true",
+            image_url: ::find_text(root, "image-url"),
+            source_description: ::find_text(root, "source-description"),
+            updated_at: ::find_text(root, "updated-at"),
+            username: ::find_text(root, "username"),
+            venmo_user_id: ::find_text(root, "venmo-user-id"),
+        })
+    }
+}
+
+/// A record detailing a request to create or update a payment method.
+///
+/// When creating a payment method, you'll need to provide a `customer_id`
+/// and a `payment_method_nonce`; the type of the resulting payment method is
+/// determined by the nonce.
+#[derive(Debug, Default)]
+pub struct Request {
+    pub billing_address: Option<::address::Address>,
+    pub billing_address_id: Option<String>,
+    pub cardholder_name: Option<String>,
+    pub customer_id: Option<String>,
+    pub cvv: Option<String>,
+    pub expiration_date: Option<String>,
+    pub expiration_month: Option<String>,
+    pub expiration_year: Option<String>,
+    /// Payment methods accept the same options as credit cards.
+    pub options: Option<::credit_card::Options>,
+    pub payment_method_nonce: Option<String>,
+    pub token: Option<String>,
+}
+
+impl ::ToXml for Request {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("payment-method"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        if let Some(ref billing_address) = self.billing_address { write!(s, "{}", billing_address.to_xml(Some("billing-address"))).unwrap(); }
+
+        write_xml!(s, "billing-address-id", self.billing_address_id);
+        write_xml!(s, "cardholder-name", self.cardholder_name);
+        write_xml!(s, "customer-id", self.customer_id);
+        write_xml!(s, "cvv", self.cvv);
+        write_xml!(s, "expiration-date", self.expiration_date);
+        write_xml!(s, "expiration-month", self.expiration_month);
+        write_xml!(s, "expiration-year", self.expiration_year);
+
+        if let Some(ref options) = self.options { write!(s, "{}", options.to_xml(None)).unwrap(); }
+
+        write_xml!(s, "payment-method-nonce", self.payment_method_nonce);
+        write_xml!(s, "token", self.token);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// A request to grant another merchant access to one of your vaulted payment
+/// methods.
+#[derive(Debug, Default)]
+pub struct GrantRequest {
+    /// The token of the payment method to share.
+    pub shared_payment_method_token: String,
+    /// Whether the receiving merchant is allowed to store the payment method
+    /// in their own vault.
+    pub allow_vaulting: Option<bool>,
+    /// Whether the billing postal code should be included with the shared
+    /// payment method.
+    pub include_billing_postal_code: Option<bool>,
+}
+
+impl ::ToXml for GrantRequest {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("payment-method"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+        write!(s, "<shared-payment-method-token>{}</shared-payment-method-token>", xml::escape(&self.shared_payment_method_token)).unwrap();
+        write_xml_type!(s, "allow-vaulting", "boolean", self.allow_vaulting);
+        write_xml_type!(s, "include-billing-postal-code", "boolean", self.include_billing_postal_code);
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
@@ -0,0 +1,141 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
+
+/// A record containing details about a payment method nonce.
+///
+/// Nonces are one-time-use references to payment information, and are most
+/// commonly generated by a client SDK. They can also be generated from a
+/// vaulted payment method using the `PaymentMethodNonceGateway`, which is
+/// useful when you need to e.g. run 3D Secure against a saved card.
+#[derive(Debug)]
+pub struct PaymentMethodNonce {
+    /// The nonce value itself, to be passed as the `payment_method_nonce`
+    /// on another request.
+    pub nonce: String,
+    /// The type of payment method the nonce refers to, e.g. `CreditCard` or
+    /// `PayPalAccount`.
+    pub typ: String,
+    pub bin_data: Option<BinData>,
+    pub details: Option<Details>,
+    /// Whether the nonce refers to the customer's default payment method.
+    pub default: Option<bool>,
+    pub three_d_secure_info: Option<ThreeDSecureInfo>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for PaymentMethodNonce {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<PaymentMethodNonce> {
+        Ok(PaymentMethodNonce{
+            nonce: ::require_text(root, "nonce")?,
+            typ: ::require_text(root, "type")?,
+            bin_data: ::decode_child(root, "bin-data", BinData::try_from)?,
+            details: ::decode_child(root, "details", Details::try_from)?,
+            default: ::find_text(root, "default").map(|s| s == "true"),
+            three_d_secure_info: match root.find("three-d-secure-info") {
+                Some(info) if info.get_attr("nil") != Some("true") => Some(::within(root, ThreeDSecureInfo::try_from(info))?),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Information about the issuing bank and card product, based on the card's
+/// BIN. Most values are one of `Yes`, `No` or `Unknown`.
+#[derive(Debug)]
+pub struct BinData {
+    pub commercial: Option<String>,
+    pub country_of_issuance: Option<String>,
+    pub debit: Option<String>,
+    pub durbin_regulated: Option<String>,
+    pub healthcare: Option<String>,
+    pub issuing_bank: Option<String>,
+    pub payroll: Option<String>,
+    pub prepaid: Option<String>,
+    pub product_id: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for BinData {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<BinData> {
+        Ok(BinData{
+            commercial: ::find_text(root, "commercial"),
+            country_of_issuance: ::find_text(root, "country-of-issuance"),
+            debit: ::find_text(root, "debit"),
+            durbin_regulated: ::find_text(root, "durbin-regulated"),
+            healthcare: ::find_text(root, "healthcare"),
+            issuing_bank: ::find_text(root, "issuing-bank"),
+            payroll: ::find_text(root, "payroll"),
+            prepaid: ::find_text(root, "prepaid"),
+            product_id: ::find_text(root, "product-id"),
+        })
+    }
+}
+
+/// Type-specific details about the payment method. Which fields are
+/// populated depends on the nonce's type; e.g. `email` is only set for PayPal
+/// accounts.
+#[derive(Debug)]
+pub struct Details {
+    pub bin: Option<String>,
+    pub card_type: Option<::credit_card::CardType>,
+    pub cardholder_name: Option<String>,
+    pub email: Option<String>,
+    pub expiration_month: Option<String>,
+    pub expiration_year: Option<String>,
+    pub last_four: Option<String>,
+    pub last_two: Option<String>,
+    pub username: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for Details {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Details> {
+        Ok(Details{
+            bin: ::find_text(root, "bin"),
+            card_type: ::find_text(root, "card-type").map(::credit_card::CardType::from),
+            cardholder_name: ::find_text(root, "cardholder-name"),
+            email: ::find_text(root, "email"),
+            expiration_month: ::find_text(root, "expiration-month"),
+            expiration_year: ::find_text(root, "expiration-year"),
+            last_four: ::find_text(root, "last-four"),
+            last_two: ::find_text(root, "last-two"),
+            username: ::find_text(root, "username"),
+        })
+    }
+}
+
+/// The result of a 3D Secure authentication associated with the nonce.
+#[derive(Debug)]
+pub struct ThreeDSecureInfo {
+    pub cavv: Option<String>,
+    pub ds_transaction_id: Option<String>,
+    pub eci_flag: Option<String>,
+    pub enrolled: Option<String>,
+    pub liability_shift_possible: Option<bool>,
+    pub liability_shifted: Option<bool>,
+    pub status: Option<String>,
+    pub three_d_secure_version: Option<String>,
+    pub xid: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for ThreeDSecureInfo {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<ThreeDSecureInfo> {
+        Ok(ThreeDSecureInfo{
+            cavv: ::find_text(root, "cavv"),
+            ds_transaction_id: ::find_text(root, "ds-transaction-id"),
+            eci_flag: ::find_text(root, "eci-flag"),
+            enrolled: ::find_text(root, "enrolled"),
+            liability_shift_possible: ::find_text(root, "liability-shift-possible").map(|s| s == "true"),
+            liability_shifted: ::find_text(root, "liability-shifted").map(|s| s == "true"),
+            status: ::find_text(root, "status"),
+            three_d_secure_version: ::find_text(root, "three-d-secure-version"),
+            xid: ::find_text(root, "xid"),
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use xml;
+
+/// A record containing plan details.
+///
+/// Plans are templates for subscriptions, defining the price and billing
+/// cycle along with any add-ons and discounts that new subscriptions inherit.
+#[derive(Debug)]
+pub struct Plan {
+    pub id: String,
+    pub add_ons: Vec<::add_on::AddOn>,
+    pub billing_day_of_month: Option<u32>,
+    /// The number of months in each billing cycle.
+    pub billing_frequency: Option<u32>,
+    pub created_at: Option<String>,
+    pub currency_iso_code: Option<String>,
+    pub description: Option<String>,
+    pub discounts: Vec<::discount::Discount>,
+    pub merchant_id: Option<String>,
+    pub name: Option<String>,
+    /// The number of billing cycles before subscriptions to this plan
+    /// expire, or `None` if they never expire.
+    pub number_of_billing_cycles: Option<u32>,
+    pub price: Option<String>,
+    pub trial_duration: Option<u32>,
+    pub trial_duration_unit: Option<String>,
+    pub trial_period: Option<bool>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for Plan {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Plan> {
+        Ok(Plan{
+            id: ::require_text(root, "id")?,
+            add_ons: ::decode_list(root, "add-ons", "add-on", ::add_on::AddOn::try_from)?,
+            billing_day_of_month: ::parse_text(root, "billing-day-of-month")?,
+            billing_frequency: ::parse_text(root, "billing-frequency")?,
+            created_at: ::find_text(root, "created-at"),
+            currency_iso_code: ::find_text(root, "currency-iso-code"),
+            description: ::find_text(root, "description"),
+            discounts: ::decode_list(root, "discounts", "discount", ::discount::Discount::try_from)?,
+            merchant_id: ::find_text(root, "merchant-id"),
+            name: ::find_text(root, "name"),
+            number_of_billing_cycles: ::parse_text(root, "number-of-billing-cycles")?,
+            price: ::find_text(root, "price"),
+            trial_duration: ::parse_text(root, "trial-duration")?,
+            trial_duration_unit: ::find_text(root, "trial-duration-unit"),
+            trial_period: ::find_text(root, "trial-period").map(|s| s == "true"),
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
+}
+
+/// A record detailing a new plan, or changes to an existing one.
+///
+/// When creating a plan, `name`, `price` and `billing_frequency` are
+/// required. When updating, only the fields that are set are changed.
+#[derive(Debug, Default)]
+pub struct Request {
+    pub add_ons: Option<::subscription::Modifications>,
+    pub billing_day_of_month: Option<u32>,
+    pub billing_frequency: Option<u32>,
+    pub currency_iso_code: Option<String>,
+    pub description: Option<String>,
+    pub discounts: Option<::subscription::Modifications>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub never_expires: Option<bool>,
+    pub number_of_billing_cycles: Option<u32>,
+    pub price: Option<String>,
+    pub trial_duration: Option<u32>,
+    /// Either `day` or `month`.
+    pub trial_duration_unit: Option<String>,
+    pub trial_period: Option<bool>,
+}
+
+impl ::ToXml for Request {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("plan"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        if let Some(ref add_ons) = self.add_ons { write!(s, "{}", add_ons.to_xml(Some("add-ons"))).unwrap(); }
+
+        write_xml!(s, "billing-day-of-month", self.billing_day_of_month);
+        write_xml!(s, "billing-frequency", self.billing_frequency);
+        write_xml!(s, "currency-iso-code", self.currency_iso_code);
+        write_xml!(s, "description", self.description);
+
+        if let Some(ref discounts) = self.discounts { write!(s, "{}", discounts.to_xml(Some("discounts"))).unwrap(); }
+
+        write_xml!(s, "id", self.id);
+        write_xml!(s, "name", self.name);
+        write_xml!(s, "never-expires", self.never_expires);
+        write_xml!(s, "number-of-billing-cycles", self.number_of_billing_cycles);
+        write_xml!(s, "price", self.price);
+        write_xml!(s, "trial-duration", self.trial_duration);
+        write_xml!(s, "trial-duration-unit", self.trial_duration_unit);
+        write_xml!(s, "trial-period", self.trial_period);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
@@ -0,0 +1,28 @@
+use std::fmt::Write;
+use xml;
+
+/// A record detailing a refund request.
+///
+/// Leaving `amount` unset refunds the full settled amount of the
+/// transaction. Specifying a smaller amount issues a partial refund, and a
+/// transaction can be partially refunded multiple times, up to its settled
+/// amount.
+#[derive(Debug, Default)]
+pub struct Request {
+    pub amount: Option<String>,
+    pub merchant_account_id: Option<String>,
+    pub order_id: Option<String>,
+}
+
+impl ::ToXml for Request {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("transaction"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+        write_xml!(s, "amount", self.amount);
+        write_xml!(s, "merchant-account-id", self.merchant_account_id);
+        write_xml!(s, "order-id", self.order_id);
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
@@ -0,0 +1,190 @@
+//! Automatic retries for requests that fail for transient reasons.
+//!
+//! Requests that are safe to repeat, such as `find` and `search`, are retried
+//! according to the client's `RetryPolicy` when the connection is dropped or
+//! times out, or the gateway responds with `503 Service Unavailable` or
+//! `429 Too Many Requests`.
+//!
+//! Requests that create or modify records are only retried if the connection
+//! to the gateway was refused, since nothing can have been sent. Once any
+//! part of the request may have reached the gateway, a failure is returned
+//! as-is: retrying a sale after a timeout could charge the customer twice.
+//! After such a failure, check whether the record was created, e.g. by
+//! searching for the transaction's `order_id`, before trying again.
+//!
+//! There is deliberately no opt-in for retrying such requests with an
+//! idempotency key. Braintree's XML API doesn't accept one, so a repeated
+//! request can't be recognised as a duplicate by the gateway and would be
+//! processed a second time.
+//!
+//! The policy is set with `builder::Builder::retry_policy`. Retries happen
+//! within the call that made the request, which sleeps the current thread
+//! between attempts. This includes calls made through
+//! `BlockingPoolBraintree`, where a thread from Tokio's blocking pool is held
+//! until the last attempt finishes. Use `RetryPolicy::none()` to disable
+//! retries entirely.
+
+use hyper;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::time::Duration;
+
+/// Controls how many times, and how often, a failed request is retried.
+///
+/// The delay before each retry doubles with each attempt, starting at
+/// `initial_backoff` and capped at `max_backoff`. If `jitter` is set, a random
+/// amount of up to half the delay is subtracted so that many clients failing
+/// at once don't all retry at the same moment.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of times a request will be sent, including the
+    /// first attempt. A value of 1 disables retries.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy{max_attempts: 1, ..Default::default()}
+    }
+
+    /// The delay before retrying a request that failed on the given attempt,
+    /// counting from 1.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        let delay = ::std::cmp::min(self.initial_backoff.checked_mul(factor).unwrap_or(self.max_backoff), self.max_backoff);
+        if self.jitter {
+            let millis = delay.as_millis() as u64 / 2;
+            if millis > 0 {
+                return delay - Duration::from_millis(random() % (millis + 1));
+            }
+        }
+        delay
+    }
+
+    /// The delay before retrying a request after the given result, or `None`
+    /// if it shouldn't be retried.
+    pub(crate) fn delay(&self, attempt: u32, retry: Retry, result: &hyper::Result<::transport::Response>) -> Option<Duration> {
+        use hyper::status::StatusCode;
+
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        if let Retry::Unsent = retry {
+            return match *result {
+                Err(hyper::Error::Io(ref err)) if err.kind() == io::ErrorKind::ConnectionRefused => Some(self.backoff(attempt)),
+                _ => None,
+            };
+        }
+        match *result {
+            Ok(ref response) => match response.status {
+                StatusCode::ServiceUnavailable|StatusCode::TooManyRequests => Some(match retry_after(response) {
+                    Some(delay) => ::std::cmp::min(delay, self.max_backoff),
+                    None => self.backoff(attempt),
+                }),
+                _ => None,
+            },
+            // Read timeouts are reported as `WouldBlock` on some platforms.
+            Err(hyper::Error::Io(ref err)) => match err.kind() {
+                io::ErrorKind::ConnectionReset|io::ErrorKind::ConnectionAborted|io::ErrorKind::BrokenPipe|io::ErrorKind::TimedOut|io::ErrorKind::UnexpectedEof|io::ErrorKind::WouldBlock => Some(self.backoff(attempt)),
+                _ => None,
+            },
+            Err(_) => None,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy{
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+}
+
+/// Which failures a request may be retried after.
+#[derive(Clone, Copy)]
+pub(crate) enum Retry {
+    /// The request has no side effects, so it can be retried after any
+    /// transient failure.
+    Safe,
+    /// The request may modify state, so it can only be retried if it
+    /// certainly wasn't sent.
+    Unsent,
+}
+
+/// The delay requested by the gateway's `Retry-After` header. Only the
+/// delay-seconds form is supported.
+fn retry_after(response: &::transport::Response) -> Option<Duration> {
+    let raw = response.headers.get_raw("Retry-After")?;
+    let value = ::std::str::from_utf8(raw.first()?).ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy{jitter: false, ..Default::default()}
+    }
+
+    fn io_error(kind: io::ErrorKind) -> hyper::Result<::transport::Response> {
+        Err(hyper::Error::Io(io::Error::new(kind, "test")))
+    }
+
+    #[test]
+    fn retries_safe_requests_after_transient_failures() {
+        assert_eq!(policy().delay(1, Retry::Safe, &io_error(io::ErrorKind::ConnectionReset)), Some(Duration::from_millis(500)));
+        assert_eq!(policy().delay(2, Retry::Safe, &io_error(io::ErrorKind::TimedOut)), Some(Duration::from_secs(1)));
+        assert_eq!(policy().delay(3, Retry::Safe, &io_error(io::ErrorKind::TimedOut)), None);
+    }
+
+    #[test]
+    fn retries_a_get_after_a_would_block_error() {
+        use hyper::status::StatusCode;
+        use std::sync::Arc;
+        use transport::mock::Scripted;
+        use transport::Response;
+
+        let transport = Arc::new(Scripted::new(vec![
+            io_error(io::ErrorKind::WouldBlock),
+            Ok(Response{
+                status: StatusCode::Ok,
+                headers: hyper::header::Headers::new(),
+                body: b"<transaction><id>abc123</id><type>sale</type><amount>10.00</amount><currency-iso-code>USD</currency-iso-code><status>settled</status><created-at>2017-01-01T00:00:00Z</created-at><updated-at>2017-01-02T00:00:00Z</updated-at></transaction>".to_vec(),
+            }),
+        ]));
+        let bt = ::Braintree::builder()
+            .environment(::Environment::Sandbox)
+            .merchant_id("merchant_id")
+            .public_key("public_key")
+            .private_key("private_key")
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy{initial_backoff: Duration::from_millis(0), ..policy()})
+            .build()
+            .unwrap();
+        let transaction = bt.transaction().find(String::from("abc123")).unwrap();
+        assert_eq!(transaction.id, "abc123");
+        assert_eq!(transport.remaining(), 0);
+    }
+
+    #[test]
+    fn only_retries_unsent_requests_when_the_connection_was_refused() {
+        assert_eq!(policy().delay(1, Retry::Unsent, &io_error(io::ErrorKind::ConnectionRefused)), Some(Duration::from_millis(500)));
+        assert_eq!(policy().delay(1, Retry::Unsent, &io_error(io::ErrorKind::ConnectionReset)), None);
+        assert_eq!(policy().delay(1, Retry::Unsent, &io_error(io::ErrorKind::TimedOut)), None);
+        assert_eq!(policy().delay(1, Retry::Unsent, &io_error(io::ErrorKind::UnexpectedEof)), None);
+    }
+}
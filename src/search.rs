@@ -0,0 +1,202 @@
+//! Types shared by the gateways that support Braintree's advanced search.
+//!
+//! Searches are described by a resource-specific record such as
+//! `transaction::Search`, whose fields are made up of the criteria defined
+//! here. Only the fields that are set will be used to filter the results:
+//!
+//! ```rust
+//! # use braintree::{search, transaction, Braintree};
+//! # fn settled_between(bt: &Braintree) -> braintree::error::Result<()> {
+//! let results = bt.transaction().search(transaction::Search{
+//!     status: vec![transaction::Status::Settled],
+//!     amount: Some(search::Range::Between(String::from("10.00"), String::from("20.00"))),
+//!     ..Default::default()
+//! })?;
+//!
+//! for transaction in results {
+//!     println!("{}", transaction?.id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use elementtree;
+use error;
+use hyper;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use xml;
+
+/// A criterion that matches against a text field.
+#[derive(Clone, Debug)]
+pub enum Text {
+    Is(String),
+    IsNot(String),
+    StartsWith(String),
+    EndsWith(String),
+    Contains(String),
+}
+
+impl ::ToXml for Text {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap());
+        let (op, value) = match *self {
+            Text::Is(ref value) => ("is", value),
+            Text::IsNot(ref value) => ("is-not", value),
+            Text::StartsWith(ref value) => ("starts-with", value),
+            Text::EndsWith(ref value) => ("ends-with", value),
+            Text::Contains(ref value) => ("contains", value),
+        };
+        let mut s = String::new();
+        write!(s, "<{}><{}>{}</{}></{}>", name, op, xml::escape(value), op, name).unwrap();
+        s
+    }
+}
+
+/// A criterion that matches against a numeric or date field. Dates should be
+/// formatted as e.g. `2017-06-01T00:00:00Z`.
+#[derive(Clone, Debug)]
+pub enum Range {
+    Is(String),
+    /// Matches values greater than or equal to the minimum.
+    Min(String),
+    /// Matches values less than or equal to the maximum.
+    Max(String),
+    /// Matches values between the minimum and maximum, inclusive.
+    Between(String, String),
+}
+
+impl ::ToXml for Range {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap());
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+        match *self {
+            Range::Is(ref value) => write!(s, "<is>{}</is>", xml::escape(value)).unwrap(),
+            Range::Min(ref min) => write!(s, "<min>{}</min>", xml::escape(min)).unwrap(),
+            Range::Max(ref max) => write!(s, "<max>{}</max>", xml::escape(max)).unwrap(),
+            Range::Between(ref min, ref max) => write!(s, "<min>{}</min><max>{}</max>", xml::escape(min), xml::escape(max)).unwrap(),
+        }
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// Builds the search body used to fetch a page of records by id.
+type PageQuery<'a> = Box<Fn(&[String]) -> String + 'a>;
+
+/// A lazy iterator over the records matched by a search.
+///
+/// The ids of every matching record are retrieved up front, and the records
+/// themselves are then fetched in pages as the iterator is consumed. If
+/// fetching a page fails, the error is yielded in place of that page's
+/// records and iteration continues with the next page.
+pub struct Results<'a, T> {
+    bt: &'a ::Braintree,
+    resource: &'static str,
+    item: &'static str,
+    page_query: PageQuery<'a>,
+    ids: Vec<String>,
+    page_size: usize,
+    position: usize,
+    buffer: VecDeque<T>,
+}
+
+impl<'a, T> Results<'a, T> where T: for<'b> TryFrom<&'b elementtree::Element, Error=::Error> {
+    /// Perform the initial search, returning the matching ids.
+    ///
+    /// `resource` is the path segment of the resource being searched, e.g.
+    /// `transactions`, and `item` is the name of the XML element for each
+    /// returned record. `page_query` builds the search body used to fetch a
+    /// page of records by id.
+    pub(crate) fn new<F>(bt: &'a ::Braintree, resource: &'static str, item: &'static str, query: String, page_query: F) -> error::Result<Results<'a, T>>
+        where F: Fn(&[String]) -> String + 'a
+    {
+        let response = bt.execute_with(hyper::method::Method::Post, &format!("{}/advanced_search_ids", resource), Some(query.as_bytes()), ::retry::Retry::Safe)?;
+        let (ids, page_size) = match response.status {
+            hyper::status::StatusCode::Ok => ::decode(bt.response_reader(response)?, |root| {
+                let ids = match root.find("ids") {
+                    Some(ids) => ids.find_all("item").map(|item| String::from(item.text())).collect(),
+                    None => return Err(::Error::Decode{element: String::from("search-results/ids"), body: String::new()}),
+                };
+                // A page size of zero would never make progress through the ids.
+                match ::require_text(root, "page-size")?.parse() {
+                    Ok(page_size) if page_size > 0 => Ok((ids, page_size)),
+                    _ => Err(::Error::Decode{element: String::from("search-results/page-size"), body: String::new()}),
+                }
+            })?,
+            _ => return Err(bt.error(response)),
+        };
+        Ok(Results{
+            bt: bt,
+            resource: resource,
+            item: item,
+            page_query: Box::new(page_query),
+            ids: ids,
+            page_size: page_size,
+            position: 0,
+            buffer: VecDeque::new(),
+        })
+    }
+
+    /// The ids of every record matched by the search.
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    fn fetch_page(&mut self) -> error::Result<()> {
+        let end = ::std::cmp::min(self.position + self.page_size, self.ids.len());
+        let query = (self.page_query)(&self.ids[self.position..end]);
+        self.position = end;
+        let response = self.bt.execute_with(hyper::method::Method::Post, &format!("{}/advanced_search", self.resource), Some(query.as_bytes()), ::retry::Retry::Safe)?;
+        match response.status {
+            hyper::status::StatusCode::Ok => {
+                let item = self.item;
+                let page: Vec<T> = ::decode(self.bt.response_reader(response)?, |root| {
+                    ::within(root, root.find_all(item).map(T::try_from).collect())
+                })?;
+                self.buffer.extend(page);
+                Ok(())
+            },
+            _ => Err(self.bt.error(response)),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Results<'a, T> where T: for<'b> TryFrom<&'b elementtree::Element, Error=::Error> {
+    type Item = error::Result<T>;
+
+    fn next(&mut self) -> Option<error::Result<T>> {
+        // A page can come back empty if its records were deleted, or no
+        // longer match, after the ids were fetched; skip past it.
+        while self.buffer.is_empty() && self.position < self.ids.len() {
+            if let Err(err) = self.fetch_page() {
+                return Some(Err(err));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper;
+    use transport::mock::{braintree, Canned};
+    use Error;
+
+    #[test]
+    fn rejects_a_page_size_of_zero() {
+        let bt = braintree(Canned{
+            status: hyper::status::StatusCode::Ok,
+            gzip: false,
+            body: "<search-results><page-size>0</page-size><ids type=\"array\"><item>abc123</item></ids></search-results>",
+        });
+        let result = bt.transaction().search(Default::default());
+        match result {
+            Err(Error::Decode{ref element, ..}) if element == "search-results/page-size" => (),
+            Err(err) => panic!("expected a page size decode error, got {:?}", err),
+            Ok(_) => panic!("expected a page size decode error"),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::fmt::Write;
+use xml;
+
+/// A record detailing a request to submit a transaction for settlement, or
+/// to update the details of a transaction that has already been submitted.
+///
+/// Leaving `amount` unset settles the full authorized amount. When
+/// submitting for settlement, the amount may be less than, but never more
+/// than, the authorized amount.
+#[derive(Debug, Default)]
+pub struct Request {
+    pub amount: Option<String>,
+    pub descriptor: Option<::descriptor::Descriptor>,
+    pub order_id: Option<String>,
+}
+
+impl ::ToXml for Request {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("transaction"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+        write_xml!(s, "amount", self.amount);
+        if let Some(ref descriptor) = self.descriptor { write!(s, "{}", descriptor.to_xml(None)).unwrap(); }
+        write_xml!(s, "order-id", self.order_id);
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
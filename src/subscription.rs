@@ -0,0 +1,398 @@
+use elementtree;
+use error;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use xml;
+
+/// A record containing subscription details.
+///
+/// A subscription charges a vaulted payment method on a recurring basis,
+/// according to the billing cycle of its plan. Each charge creates a
+/// transaction, the most recent of which are included in `transactions`.
+#[derive(Debug)]
+pub struct Subscription {
+    pub id: String,
+    pub status: Status,
+    pub add_ons: Vec<::add_on::AddOn>,
+    /// The amount outstanding on the subscription, e.g. after a failed
+    /// charge.
+    pub balance: Option<String>,
+    pub billing_day_of_month: Option<u32>,
+    pub billing_period_end_date: Option<String>,
+    pub billing_period_start_date: Option<String>,
+    pub created_at: Option<String>,
+    pub current_billing_cycle: Option<u32>,
+    pub days_past_due: Option<u32>,
+    pub descriptor: Option<::descriptor::Descriptor>,
+    pub description: Option<String>,
+    pub discounts: Vec<::discount::Discount>,
+    /// The number of consecutive failed charges.
+    pub failure_count: Option<u32>,
+    pub first_billing_date: Option<String>,
+    pub merchant_account_id: Option<String>,
+    pub never_expires: Option<bool>,
+    /// The total amount of the next charge, including any outstanding
+    /// balance.
+    pub next_bill_amount: Option<String>,
+    pub next_billing_date: Option<String>,
+    /// The amount of the next billing period, including add-ons and
+    /// discounts but not any outstanding balance.
+    pub next_billing_period_amount: Option<String>,
+    pub number_of_billing_cycles: Option<u32>,
+    pub paid_through_date: Option<String>,
+    pub payment_method_token: Option<String>,
+    pub plan_id: Option<String>,
+    pub price: Option<String>,
+    pub status_history: Vec<StatusEvent>,
+    pub transactions: Vec<::transaction::Transaction>,
+    pub trial_duration: Option<u32>,
+    pub trial_duration_unit: Option<String>,
+    pub trial_period: Option<bool>,
+    pub updated_at: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for Subscription {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Subscription> {
+        Ok(Subscription{
+            id: ::require_text(root, "id")?,
+            status: Status::from(::require_text(root, "status")?),
+            add_ons: ::decode_list(root, "add-ons", "add-on", ::add_on::AddOn::try_from)?,
+            balance: ::find_text(root, "balance"),
+            billing_day_of_month: ::parse_text(root, "billing-day-of-month")?,
+            billing_period_end_date: ::find_text(root, "billing-period-end-date"),
+            billing_period_start_date: ::find_text(root, "billing-period-start-date"),
+            created_at: ::find_text(root, "created-at"),
+            current_billing_cycle: ::parse_text(root, "current-billing-cycle")?,
+            days_past_due: ::parse_text(root, "days-past-due")?,
+            descriptor: ::decode_child(root, "descriptor", ::descriptor::Descriptor::try_from)?,
+            description: ::find_text(root, "description"),
+            discounts: ::decode_list(root, "discounts", "discount", ::discount::Discount::try_from)?,
+            failure_count: ::parse_text(root, "failure-count")?,
+            first_billing_date: ::find_text(root, "first-billing-date"),
+            merchant_account_id: ::find_text(root, "merchant-account-id"),
+            never_expires: ::find_text(root, "never-expires").map(|s| s == "true"),
+            next_bill_amount: ::find_text(root, "next-bill-amount"),
+            next_billing_date: ::find_text(root, "next-billing-date"),
+            next_billing_period_amount: ::find_text(root, "next-billing-period-amount"),
+            number_of_billing_cycles: ::parse_text(root, "number-of-billing-cycles")?,
+            paid_through_date: ::find_text(root, "paid-through-date"),
+            payment_method_token: ::find_text(root, "payment-method-token"),
+            plan_id: ::find_text(root, "plan-id"),
+            price: ::find_text(root, "price"),
+            status_history: ::decode_list(root, "status-history", "status-event", StatusEvent::try_from)?,
+            transactions: ::decode_list(root, "transactions", "transaction", ::transaction::Transaction::try_from)?,
+            trial_duration: ::parse_text(root, "trial-duration")?,
+            trial_duration_unit: ::find_text(root, "trial-duration-unit"),
+            trial_period: ::find_text(root, "trial-period").map(|s| s == "true"),
+            updated_at: ::find_text(root, "updated-at"),
+        })
+    }
+}
+
+/// A change in a subscription's status, such as it being canceled or going
+/// past due.
+#[derive(Debug)]
+pub struct StatusEvent {
+    pub status: Status,
+    pub timestamp: String,
+    pub balance: Option<String>,
+    pub currency_iso_code: Option<String>,
+    pub plan_id: Option<String>,
+    pub price: Option<String>,
+    pub subscription_source: Option<String>,
+    pub user: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for StatusEvent {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<StatusEvent> {
+        Ok(StatusEvent{
+            status: Status::from(::require_text(root, "status")?),
+            timestamp: ::require_text(root, "timestamp")?,
+            balance: ::find_text(root, "balance"),
+            currency_iso_code: ::find_text(root, "currency-iso-code"),
+            plan_id: ::find_text(root, "plan-id"),
+            price: ::find_text(root, "price"),
+            subscription_source: ::find_text(root, "subscription-source"),
+            user: ::find_text(root, "user"),
+        })
+    }
+}
+
+/// A record detailing a new subscription, or changes to an existing one.
+///
+/// When creating a subscription, `plan_id` and either `payment_method_token`
+/// or `payment_method_nonce` are required; any values that aren't set are
+/// inherited from the plan. When updating, only the fields that are set are
+/// changed.
+#[derive(Debug, Default)]
+pub struct Request {
+    pub add_ons: Option<Modifications>,
+    pub billing_day_of_month: Option<u32>,
+    pub descriptor: Option<::descriptor::Descriptor>,
+    pub discounts: Option<Modifications>,
+    pub first_billing_date: Option<String>,
+    pub id: Option<String>,
+    pub merchant_account_id: Option<String>,
+    pub never_expires: Option<bool>,
+    pub number_of_billing_cycles: Option<u32>,
+    pub options: Option<Options>,
+    pub payment_method_nonce: Option<String>,
+    pub payment_method_token: Option<String>,
+    pub plan_id: Option<String>,
+    pub price: Option<String>,
+    pub trial_duration: Option<u32>,
+    /// Either `day` or `month`.
+    pub trial_duration_unit: Option<String>,
+    pub trial_period: Option<bool>,
+}
+
+impl ::ToXml for Request {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("subscription"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        if let Some(ref add_ons) = self.add_ons { write!(s, "{}", add_ons.to_xml(Some("add-ons"))).unwrap(); }
+
+        write_xml!(s, "billing-day-of-month", self.billing_day_of_month);
+
+        if let Some(ref descriptor) = self.descriptor { write!(s, "{}", descriptor.to_xml(None)).unwrap(); }
+
+        if let Some(ref discounts) = self.discounts { write!(s, "{}", discounts.to_xml(Some("discounts"))).unwrap(); }
+
+        write_xml!(s, "first-billing-date", self.first_billing_date);
+        write_xml!(s, "id", self.id);
+        write_xml!(s, "merchant-account-id", self.merchant_account_id);
+        write_xml!(s, "never-expires", self.never_expires);
+        write_xml!(s, "number-of-billing-cycles", self.number_of_billing_cycles);
+
+        if let Some(ref options) = self.options { write!(s, "{}", options.to_xml(None)).unwrap(); }
+
+        write_xml!(s, "payment-method-nonce", self.payment_method_nonce);
+        write_xml!(s, "payment-method-token", self.payment_method_token);
+        write_xml!(s, "plan-id", self.plan_id);
+        write_xml!(s, "price", self.price);
+        write_xml!(s, "trial-duration", self.trial_duration);
+        write_xml!(s, "trial-duration-unit", self.trial_duration_unit);
+        write_xml!(s, "trial-period", self.trial_period);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Don't inherit the plan's add-ons and discounts when creating the
+    /// subscription.
+    pub do_not_inherit_add_ons_or_discounts: Option<bool>,
+    /// Whether to charge, or credit, the prorated difference when changing
+    /// the price of a subscription mid-cycle.
+    pub prorate_charges: Option<bool>,
+    /// Replace all of the subscription's add-ons and discounts with those of
+    /// its new plan when changing plans.
+    pub replace_all_add_ons_and_discounts: Option<bool>,
+    /// Leave the subscription unchanged if the prorated charge fails.
+    pub revert_subscription_on_proration_failure: Option<bool>,
+    /// Charge the first billing period immediately, even if the plan has a
+    /// later billing day of the month.
+    pub start_immediately: Option<bool>,
+}
+
+impl ::ToXml for Options {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("options"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write_xml!(s, "do-not-inherit-add-ons-or-discounts", self.do_not_inherit_add_ons_or_discounts);
+        write_xml!(s, "prorate-charges", self.prorate_charges);
+        write_xml!(s, "replace-all-add-ons-and-discounts", self.replace_all_add_ons_and_discounts);
+        write_xml!(s, "revert-subscription-on-proration-failure", self.revert_subscription_on_proration_failure);
+        write_xml!(s, "start-immediately", self.start_immediately);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// Changes to a subscription's add-ons or discounts.
+#[derive(Debug, Default)]
+pub struct Modifications {
+    /// Add-ons or discounts to attach to the subscription.
+    pub add: Vec<NewModification>,
+    /// Changes to add-ons or discounts already on the subscription.
+    pub update: Vec<ModificationUpdate>,
+    /// The ids of add-ons or discounts to remove from the subscription.
+    pub remove: Vec<String>,
+}
+
+impl ::ToXml for Modifications {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("add-ons"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        if !self.add.is_empty() {
+            write!(s, "<add type=\"array\">").unwrap();
+            for modification in &self.add { write!(s, "{}", modification.to_xml(None)).unwrap(); }
+            write!(s, "</add>").unwrap();
+        }
+
+        if !self.update.is_empty() {
+            write!(s, "<update type=\"array\">").unwrap();
+            for modification in &self.update { write!(s, "{}", modification.to_xml(None)).unwrap(); }
+            write!(s, "</update>").unwrap();
+        }
+
+        write_xml_array!(s, "remove", self.remove);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// An add-on or discount to attach to a subscription. Any values that aren't
+/// set are inherited from the add-on or discount with id `inherited_from_id`.
+#[derive(Debug, Default)]
+pub struct NewModification {
+    pub inherited_from_id: String,
+    pub amount: Option<String>,
+    pub never_expires: Option<bool>,
+    pub number_of_billing_cycles: Option<u32>,
+    pub quantity: Option<u32>,
+}
+
+impl ::ToXml for NewModification {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("add"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write!(s, "<inherited-from-id>{}</inherited-from-id>", xml::escape(&self.inherited_from_id)).unwrap();
+        write_xml!(s, "amount", self.amount);
+        write_xml!(s, "never-expires", self.never_expires);
+        write_xml!(s, "number-of-billing-cycles", self.number_of_billing_cycles);
+        write_xml!(s, "quantity", self.quantity);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// Changes to an add-on or discount that's already attached to a
+/// subscription, identified by `existing_id`.
+#[derive(Debug, Default)]
+pub struct ModificationUpdate {
+    pub existing_id: String,
+    pub amount: Option<String>,
+    pub never_expires: Option<bool>,
+    pub number_of_billing_cycles: Option<u32>,
+    pub quantity: Option<u32>,
+}
+
+impl ::ToXml for ModificationUpdate {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("update"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write!(s, "<existing-id>{}</existing-id>", xml::escape(&self.existing_id)).unwrap();
+        write_xml!(s, "amount", self.amount);
+        write_xml!(s, "never-expires", self.never_expires);
+        write_xml!(s, "number-of-billing-cycles", self.number_of_billing_cycles);
+        write_xml!(s, "quantity", self.quantity);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// A request to retry charging a past-due subscription. If `amount` isn't
+/// set, the subscription's outstanding balance is charged.
+#[derive(Debug, Default)]
+pub struct RetryCharge {
+    pub subscription_id: String,
+    pub amount: Option<String>,
+    pub submit_for_settlement: Option<bool>,
+}
+
+impl ::ToXml for RetryCharge {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("transaction"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write!(s, "<type>{}</type>", String::from(::transaction::Type::Sale)).unwrap();
+        write!(s, "<subscription-id>{}</subscription-id>", xml::escape(&self.subscription_id)).unwrap();
+        write_xml!(s, "amount", self.amount);
+        if let Some(submit_for_settlement) = self.submit_for_settlement {
+            write!(s, "<options><submit-for-settlement>{}</submit-for-settlement></options>", submit_for_settlement).unwrap();
+        }
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+/// A record describing the criteria for a subscription search.
+///
+/// As with `transaction::Search`, multiple-value criteria are ignored when
+/// left empty.
+#[derive(Clone, Debug, Default)]
+pub struct Search {
+    pub billing_cycles_remaining: Option<::search::Range>,
+    pub created_at: Option<::search::Range>,
+    pub days_past_due: Option<::search::Range>,
+    pub id: Option<::search::Text>,
+    pub ids: Vec<String>,
+    /// If set, only match subscriptions that either are (`true`) or are not
+    /// (`false`) in their trial period.
+    pub in_trial_period: Option<bool>,
+    pub merchant_account_id: Vec<String>,
+    pub next_billing_date: Option<::search::Range>,
+    pub plan_id: Vec<String>,
+    pub price: Option<::search::Range>,
+    pub status: Vec<Status>,
+    pub transaction_id: Option<::search::Text>,
+}
+
+impl ::ToXml for Search {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("search"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write_xml_criterion!(s, "billing-cycles-remaining", self.billing_cycles_remaining);
+        write_xml_criterion!(s, "created-at", self.created_at);
+        write_xml_criterion!(s, "days-past-due", self.days_past_due);
+        write_xml_criterion!(s, "id", self.id);
+        write_xml_array!(s, "ids", self.ids);
+        if let Some(in_trial_period) = self.in_trial_period {
+            write!(s, "<in-trial-period type=\"array\"><item>{}</item></in-trial-period>", in_trial_period).unwrap();
+        }
+        write_xml_array!(s, "merchant-account-id", self.merchant_account_id);
+        write_xml_criterion!(s, "next-billing-date", self.next_billing_date);
+        write_xml_array!(s, "plan-id", self.plan_id);
+        write_xml_criterion!(s, "price", self.price);
+        write_xml_array!(s, "status", self.status);
+        write_xml_criterion!(s, "transaction-id", self.transaction_id);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
+string_enum! {
+    pub enum Status {
+        Active => "active",
+        Canceled => "canceled",
+        Expired => "expired",
+        PastDue => "past_due",
+        Pending => "pending",
+    }
+}
@@ -1,11 +1,14 @@
 use elementtree;
-use std::convert::From;
+use error;
+use std::convert::TryFrom;
 use std::collections::HashMap;
-use std::io::Read;
 use std::fmt::Write;
 use xml;
 
 /// A record containing transaction details.
+///
+/// Fields that the gateway may leave empty, depending on the type of
+/// transaction and how far it has progressed, are modelled as `Option`s.
 #[derive(Debug)]
 pub struct Transaction {
     pub id: String,
@@ -13,18 +16,183 @@ pub struct Transaction {
     pub amount: String, // change to a decmial?
     pub currency_iso_code: String,
     pub status: Status,
+    pub created_at: String,
+    pub updated_at: String,
+    pub additional_processor_response: Option<String>,
+    pub avs_error_response_code: Option<String>,
+    pub avs_postal_code_response_code: Option<String>,
+    pub avs_street_address_response_code: Option<String>,
+    pub billing: Option<::address::Address>,
+    pub credit_card: Option<::credit_card::CreditCard>,
+    pub custom_fields: HashMap<String, String>,
+    pub customer: Option<::customer::Customer>,
+    pub cvv_response_code: Option<String>,
+    pub descriptor: Option<::descriptor::Descriptor>,
+    pub disbursement_details: Option<DisbursementDetails>,
+    pub gateway_rejection_reason: Option<GatewayRejectionReason>,
+    pub merchant_account_id: Option<String>,
+    pub network_transaction_id: Option<String>,
+    pub order_id: Option<String>,
+    pub payment_instrument_type: Option<String>,
+    pub plan_id: Option<String>,
+    pub processor_authorization_code: Option<String>,
+    pub processor_response_code: Option<String>,
+    pub processor_response_text: Option<String>,
+    pub processor_settlement_response_code: Option<String>,
+    pub processor_settlement_response_text: Option<String>,
+    pub purchase_order_number: Option<String>,
+    pub recurring: Option<bool>,
+    /// The ids of any credit transactions created by refunding this
+    /// transaction.
+    pub refund_ids: Vec<String>,
+    /// If this transaction is a refund, the id of the transaction that was
+    /// refunded.
+    pub refunded_transaction_id: Option<String>,
+    pub risk_data: Option<RiskData>,
+    pub service_fee_amount: Option<String>,
+    pub settlement_batch_id: Option<String>,
+    pub shipping: Option<::address::Address>,
+    /// Every status that this transaction has passed through, oldest first.
+    pub status_history: Vec<StatusEvent>,
+    pub subscription_id: Option<String>,
+    pub tax_amount: Option<String>,
+    pub tax_exempt: Option<bool>,
 }
 
-impl From<Box<Read>> for Transaction {
-    fn from(xml: Box<Read>) -> Transaction {
-        let root = elementtree::Element::from_reader(xml).unwrap();
-        Transaction{
-            id: String::from(root.find("id").unwrap().text()),
-            typ: Type::from(String::from(root.find("type").unwrap().text())),
-            amount: String::from(root.find("amount").unwrap().text()),
-            currency_iso_code: String::from(root.find("currency-iso-code").unwrap().text()),
-            status: Status::from(String::from(root.find("status").unwrap().text())),
-        }
+impl<'a> TryFrom<&'a elementtree::Element> for Transaction {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Transaction> {
+        Ok(Transaction{
+            id: ::require_text(root, "id")?,
+            typ: Type::from(::require_text(root, "type")?),
+            amount: ::require_text(root, "amount")?,
+            currency_iso_code: ::require_text(root, "currency-iso-code")?,
+            status: Status::from(::require_text(root, "status")?),
+            created_at: ::require_text(root, "created-at")?,
+            updated_at: ::require_text(root, "updated-at")?,
+            additional_processor_response: ::find_text(root, "additional-processor-response"),
+            avs_error_response_code: ::find_text(root, "avs-error-response-code"),
+            avs_postal_code_response_code: ::find_text(root, "avs-postal-code-response-code"),
+            avs_street_address_response_code: ::find_text(root, "avs-street-address-response-code"),
+            billing: ::decode_child(root, "billing", ::address::Address::try_from)?,
+            credit_card: ::decode_child(root, "credit-card", ::credit_card::CreditCard::try_from)?,
+            custom_fields: match root.find("custom-fields") {
+                Some(custom_fields) => custom_fields.children().map(|field| (String::from(field.tag().name()), String::from(field.text()))).collect(),
+                None => HashMap::new(),
+            },
+            customer: ::decode_child(root, "customer", ::customer::Customer::try_from)?,
+            cvv_response_code: ::find_text(root, "cvv-response-code"),
+            descriptor: ::decode_child(root, "descriptor", ::descriptor::Descriptor::try_from)?,
+            disbursement_details: ::decode_child(root, "disbursement-details", DisbursementDetails::try_from)?.and_then(|details| {
+                // The gateway always includes this element, but it's empty
+                // until the transaction has actually been disbursed.
+                if details.disbursement_date.is_some() { Some(details) } else { None }
+            }),
+            gateway_rejection_reason: ::find_text(root, "gateway-rejection-reason").map(GatewayRejectionReason::from),
+            merchant_account_id: ::find_text(root, "merchant-account-id"),
+            network_transaction_id: ::find_text(root, "network-transaction-id"),
+            order_id: ::find_text(root, "order-id"),
+            payment_instrument_type: ::find_text(root, "payment-instrument-type"),
+            plan_id: ::find_text(root, "plan-id"),
+            processor_authorization_code: ::find_text(root, "processor-authorization-code"),
+            processor_response_code: ::find_text(root, "processor-response-code"),
+            processor_response_text: ::find_text(root, "processor-response-text"),
+            processor_settlement_response_code: ::find_text(root, "processor-settlement-response-code"),
+            processor_settlement_response_text: ::find_text(root, "processor-settlement-response-text"),
+            purchase_order_number: ::find_text(root, "purchase-order-number"),
+            recurring: ::find_text(root, "recurring").map(|s| s == "true"),
+            refund_ids: match root.find("refund-ids") {
+                Some(refund_ids) => refund_ids.find_all("item").map(|item| String::from(item.text())).collect(),
+                None => Vec::new(),
+            },
+            refunded_transaction_id: ::find_text(root, "refunded-transaction-id"),
+            risk_data: match root.find("risk-data") {
+                Some(risk_data) if risk_data.get_attr("nil") != Some("true") => Some(::within(root, RiskData::try_from(risk_data))?),
+                _ => None,
+            },
+            service_fee_amount: ::find_text(root, "service-fee-amount"),
+            settlement_batch_id: ::find_text(root, "settlement-batch-id"),
+            shipping: ::decode_child(root, "shipping", ::address::Address::try_from)?,
+            status_history: ::decode_list(root, "status-history", "status-event", StatusEvent::try_from)?,
+            subscription_id: ::find_text(root, "subscription-id"),
+            tax_amount: ::find_text(root, "tax-amount"),
+            tax_exempt: ::find_text(root, "tax-exempt").map(|s| s == "true"),
+        })
+    }
+}
+
+/// Details about when and how a transaction's funds were disbursed to your
+/// bank account.
+#[derive(Debug)]
+pub struct DisbursementDetails {
+    pub disbursement_date: Option<String>,
+    pub funds_held: Option<bool>,
+    pub settlement_amount: Option<String>,
+    pub settlement_currency_exchange_rate: Option<String>,
+    pub settlement_currency_iso_code: Option<String>,
+    pub success: Option<bool>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for DisbursementDetails {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<DisbursementDetails> {
+        Ok(DisbursementDetails{
+            disbursement_date: ::find_text(root, "disbursement-date"),
+            funds_held: ::find_text(root, "funds-held").map(|s| s == "true"),
+            settlement_amount: ::find_text(root, "settlement-amount"),
+            settlement_currency_exchange_rate: ::find_text(root, "settlement-currency-exchange-rate"),
+            settlement_currency_iso_code: ::find_text(root, "settlement-currency-iso-code"),
+            success: ::find_text(root, "success").map(|s| s == "true"),
+        })
+    }
+}
+
+/// The result of the fraud checks performed on a transaction.
+#[derive(Debug)]
+pub struct RiskData {
+    pub id: Option<String>,
+    /// The risk decision, e.g. `Approve` or `Decline`.
+    pub decision: Option<String>,
+    pub device_data_captured: Option<bool>,
+    pub fraud_service_provider: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for RiskData {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<RiskData> {
+        Ok(RiskData{
+            id: ::find_text(root, "id"),
+            decision: ::find_text(root, "decision"),
+            device_data_captured: ::find_text(root, "device-data-captured").map(|s| s == "true"),
+            fraud_service_provider: ::find_text(root, "fraud-service-provider"),
+        })
+    }
+}
+
+/// A single entry in a transaction's status history.
+#[derive(Debug)]
+pub struct StatusEvent {
+    pub status: Status,
+    pub timestamp: String,
+    pub amount: Option<String>,
+    pub transaction_source: Option<String>,
+    pub user: Option<String>,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for StatusEvent {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<StatusEvent> {
+        Ok(StatusEvent{
+            status: Status::from(::require_text(root, "status")?),
+            timestamp: ::require_text(root, "timestamp")?,
+            amount: ::find_text(root, "amount"),
+            transaction_source: ::find_text(root, "transaction-source"),
+            user: ::find_text(root, "user"),
+        })
     }
 }
 
@@ -69,7 +237,7 @@ impl ::ToXml for Request {
         let mut s = String::new();
         write!(s, "<{}>", name).unwrap();
 
-        write!(s, "<type>{}</type>", String::from(self.typ)).unwrap();
+        write!(s, "<type>{}</type>", String::from(self.typ.clone())).unwrap();
         write!(s, "<amount>{}</amount>", xml::escape(&self.amount)).unwrap();
         write_xml!(s, "order-id", self.order_id);
         write_xml!(s, "billing-address-id", self.billing_address_id);
@@ -147,15 +315,135 @@ impl ::ToXml for Options {
     }
 }
 
+/// A record describing the criteria for a transaction search.
+///
+/// As with `Request`, you'll want to use the `Default` trait to fill out
+/// only the criteria you need. Multiple-value criteria, such as `status`, are
+/// ignored when left empty.
+#[derive(Clone, Debug, Default)]
+pub struct Search {
+    pub amount: Option<::search::Range>,
+    pub authorization_expired_at: Option<::search::Range>,
+    pub authorized_at: Option<::search::Range>,
+    pub billing_company: Option<::search::Text>,
+    pub billing_country_name: Option<::search::Text>,
+    pub billing_extended_address: Option<::search::Text>,
+    pub billing_first_name: Option<::search::Text>,
+    pub billing_last_name: Option<::search::Text>,
+    pub billing_locality: Option<::search::Text>,
+    pub billing_postal_code: Option<::search::Text>,
+    pub billing_region: Option<::search::Text>,
+    pub billing_street_address: Option<::search::Text>,
+    pub created_at: Option<::search::Range>,
+    pub credit_card_cardholder_name: Option<::search::Text>,
+    pub credit_card_expiration_date: Option<::search::Text>,
+    pub credit_card_number: Option<::search::Text>,
+    pub currency: Option<::search::Text>,
+    pub customer_company: Option<::search::Text>,
+    pub customer_email: Option<::search::Text>,
+    pub customer_fax: Option<::search::Text>,
+    pub customer_first_name: Option<::search::Text>,
+    pub customer_id: Option<::search::Text>,
+    pub customer_last_name: Option<::search::Text>,
+    pub customer_phone: Option<::search::Text>,
+    pub customer_website: Option<::search::Text>,
+    pub failed_at: Option<::search::Range>,
+    pub gateway_rejected_at: Option<::search::Range>,
+    pub ids: Vec<String>,
+    pub merchant_account_id: Vec<String>,
+    pub order_id: Option<::search::Text>,
+    pub payment_method_token: Option<::search::Text>,
+    pub processor_declined_at: Option<::search::Range>,
+    /// If set, only match transactions that either have (`true`) or have not
+    /// (`false`) been refunded.
+    pub refund: Option<bool>,
+    pub settled_at: Option<::search::Range>,
+    pub settlement_batch_id: Option<::search::Text>,
+    pub shipping_company: Option<::search::Text>,
+    pub shipping_country_name: Option<::search::Text>,
+    pub shipping_extended_address: Option<::search::Text>,
+    pub shipping_first_name: Option<::search::Text>,
+    pub shipping_last_name: Option<::search::Text>,
+    pub shipping_locality: Option<::search::Text>,
+    pub shipping_postal_code: Option<::search::Text>,
+    pub shipping_region: Option<::search::Text>,
+    pub shipping_street_address: Option<::search::Text>,
+    pub status: Vec<Status>,
+    pub submitted_for_settlement_at: Option<::search::Range>,
+    pub typ: Vec<Type>,
+    pub voided_at: Option<::search::Range>,
+}
+
+impl ::ToXml for Search {
+    fn to_xml(&self, name: Option<&str>) -> String {
+        let name = xml::escape(&name.unwrap_or("search"));
+        let mut s = String::new();
+        write!(s, "<{}>", name).unwrap();
+
+        write_xml_criterion!(s, "amount", self.amount);
+        write_xml_criterion!(s, "authorization-expired-at", self.authorization_expired_at);
+        write_xml_criterion!(s, "authorized-at", self.authorized_at);
+        write_xml_criterion!(s, "billing-company", self.billing_company);
+        write_xml_criterion!(s, "billing-country-name", self.billing_country_name);
+        write_xml_criterion!(s, "billing-extended-address", self.billing_extended_address);
+        write_xml_criterion!(s, "billing-first-name", self.billing_first_name);
+        write_xml_criterion!(s, "billing-last-name", self.billing_last_name);
+        write_xml_criterion!(s, "billing-locality", self.billing_locality);
+        write_xml_criterion!(s, "billing-postal-code", self.billing_postal_code);
+        write_xml_criterion!(s, "billing-region", self.billing_region);
+        write_xml_criterion!(s, "billing-street-address", self.billing_street_address);
+        write_xml_criterion!(s, "created-at", self.created_at);
+        write_xml_criterion!(s, "credit-card-cardholder-name", self.credit_card_cardholder_name);
+        write_xml_criterion!(s, "credit-card-expiration-date", self.credit_card_expiration_date);
+        write_xml_criterion!(s, "credit-card-number", self.credit_card_number);
+        write_xml_criterion!(s, "currency", self.currency);
+        write_xml_criterion!(s, "customer-company", self.customer_company);
+        write_xml_criterion!(s, "customer-email", self.customer_email);
+        write_xml_criterion!(s, "customer-fax", self.customer_fax);
+        write_xml_criterion!(s, "customer-first-name", self.customer_first_name);
+        write_xml_criterion!(s, "customer-id", self.customer_id);
+        write_xml_criterion!(s, "customer-last-name", self.customer_last_name);
+        write_xml_criterion!(s, "customer-phone", self.customer_phone);
+        write_xml_criterion!(s, "customer-website", self.customer_website);
+        write_xml_criterion!(s, "failed-at", self.failed_at);
+        write_xml_criterion!(s, "gateway-rejected-at", self.gateway_rejected_at);
+        write_xml_array!(s, "ids", self.ids);
+        write_xml_array!(s, "merchant-account-id", self.merchant_account_id);
+        write_xml_criterion!(s, "order-id", self.order_id);
+        write_xml_criterion!(s, "payment-method-token", self.payment_method_token);
+        write_xml_criterion!(s, "processor-declined-at", self.processor_declined_at);
+        write_xml!(s, "refund", self.refund);
+        write_xml_criterion!(s, "settled-at", self.settled_at);
+        write_xml_criterion!(s, "settlement-batch-id", self.settlement_batch_id);
+        write_xml_criterion!(s, "shipping-company", self.shipping_company);
+        write_xml_criterion!(s, "shipping-country-name", self.shipping_country_name);
+        write_xml_criterion!(s, "shipping-extended-address", self.shipping_extended_address);
+        write_xml_criterion!(s, "shipping-first-name", self.shipping_first_name);
+        write_xml_criterion!(s, "shipping-last-name", self.shipping_last_name);
+        write_xml_criterion!(s, "shipping-locality", self.shipping_locality);
+        write_xml_criterion!(s, "shipping-postal-code", self.shipping_postal_code);
+        write_xml_criterion!(s, "shipping-region", self.shipping_region);
+        write_xml_criterion!(s, "shipping-street-address", self.shipping_street_address);
+        write_xml_array!(s, "status", self.status);
+        write_xml_criterion!(s, "submitted-for-settlement-at", self.submitted_for_settlement_at);
+        write_xml_array!(s, "type", self.typ);
+        write_xml_criterion!(s, "voided-at", self.voided_at);
+
+        write!(s, "</{}>", name).unwrap();
+        s
+    }
+}
+
 // TODO: implement this and add it to Options above
 // pub struct PayPalOptions {
 //     
 // }
 
-#[derive(Copy, Clone, Debug)]
-pub enum Type {
-    Sale,
-    Credit,
+string_enum! {
+    pub enum Type {
+        Sale => "sale",
+        Credit => "credit",
+    }
 }
 
 impl Default for Type {
@@ -164,82 +452,41 @@ impl Default for Type {
     }
 }
 
-impl From<String> for Type {
-    fn from(s: String) -> Type {
-        match s.as_ref() {
-            "sale" => Type::Sale,
-            "credit" => Type::Credit,
-            _ => panic!("unknown transaction type: {}", s),
-        }
+string_enum! {
+    pub enum Status {
+        AuthorizationExpired => "authorization_expired",
+        Authorizing => "authorizing",
+        Authorized => "authorized",
+        GatewayRejected => "gateway_rejected",
+        Failed => "failed",
+        ProcessorDeclined => "processor_declined",
+        Settled => "settled",
+        SettlementConfirmed => "settlement_confirmed",
+        SettlementDeclined => "settlement_declined",
+        SettlementPending => "settlement_pending",
+        Settling => "settling",
+        SubmittedForSettlement => "submitted_for_settlement",
+        Voided => "voided",
+        /// The gateway itself was unable to determine the transaction's
+        /// status. This is distinct from `Unknown`, which is used for
+        /// statuses that this library doesn't know about.
+        Unrecognized => "unrecognized",
     }
 }
 
-impl From<Type> for String {
-    fn from(t: Type) -> String {
-        match t {
-            Type::Sale => String::from("sale"),
-            Type::Credit => String::from("credit"),
-        }
-    }
-}
-
-#[derive(Copy, Clone, Debug)]
-pub enum Status {
-    AuthorizationExpired,
-    Authorizing,
-    Authorized,
-    GatewayRejected,
-    Failed,
-    ProcessorDeclined,
-    Settled,
-    SettlementConfirmed,
-    SettlementDeclined,
-    SettlementPending,
-    Settling,
-    SubmittedForSettlement,
-    Voided,
-    Unrecognized,
-}
-
-impl From<String> for Status {
-    fn from(s: String) -> Status {
-        match s.as_ref() {
-            "authorization_expired" => Status::AuthorizationExpired,
-            "authorizing" => Status::Authorizing,
-            "authorized" => Status::Authorized,
-            "gateway_rejected" => Status::GatewayRejected,
-            "failed" => Status::Failed,
-            "processor_declined" => Status::ProcessorDeclined,
-            "settled" => Status::Settled,
-            "settlement_confirmed" => Status::SettlementConfirmed,
-            "settlement_declined" => Status::SettlementDeclined,
-            "settlement_pending" => Status::SettlementPending,
-            "settling" => Status::Settling,
-            "submitted_for_settlement" => Status::SubmittedForSettlement,
-            "voided" => Status::Voided,
-            "unrecognized" => Status::Unrecognized,
-            _ => panic!("unknown transaction status: {}", s),
-        }
-    }
-}
-
-impl From<Status> for String {
-    fn from(s: Status) -> String {
-        match s {
-            Status::AuthorizationExpired => String::from("authorization_expired"),
-            Status::Authorizing => String::from("authorizing"),
-            Status::Authorized => String::from("authorized"),
-            Status::GatewayRejected => String::from("gateway_rejected"),
-            Status::Failed => String::from("failed"),
-            Status::ProcessorDeclined => String::from("processor_declined"),
-            Status::Settled => String::from("settled"),
-            Status::SettlementConfirmed => String::from("settlement_confirmed"),
-            Status::SettlementDeclined => String::from("settlement_declined"),
-            Status::SettlementPending => String::from("settlement_pending"),
-            Status::Settling => String::from("settling"),
-            Status::SubmittedForSettlement => String::from("submitted_for_settlement"),
-            Status::Voided => String::from("voided"),
-            Status::Unrecognized => String::from("unrecognized"),
-        }
+string_enum! {
+    /// The reason that a transaction was rejected by the gateway, based on
+    /// your account's fraud and risk settings.
+    pub enum GatewayRejectionReason {
+        ApplicationIncomplete => "application_incomplete",
+        Avs => "avs",
+        AvsAndCvv => "avs_and_cvv",
+        Cvv => "cvv",
+        Duplicate => "duplicate",
+        ExcessiveRetry => "excessive_retry",
+        Fraud => "fraud",
+        RiskThreshold => "risk_threshold",
+        ThreeDSecure => "three_d_secure",
+        TokenIssuance => "token_issuance",
     }
 }
@@ -0,0 +1,237 @@
+//! The HTTP layer used to talk to the gateway.
+//!
+//! By default, requests are sent using `hyper` with native TLS, but any type
+//! implementing `HttpTransport` can be plugged in using
+//! `builder::Builder::transport`. This is useful for routing requests through
+//! a custom client, using a different TLS implementation, or returning canned
+//! responses in tests.
+
+use hyper;
+use hyper_native_tls;
+use std::io::Read;
+
+/// An HTTP request to be sent to the gateway.
+pub struct Request<'a> {
+    pub method: hyper::method::Method,
+    pub url: hyper::Url,
+    pub headers: hyper::header::Headers,
+    pub body: Option<&'a [u8]>,
+}
+
+/// An HTTP response received from the gateway. The body is returned exactly
+/// as it was received; any `Content-Encoding` is handled by the caller.
+pub struct Response {
+    pub status: hyper::status::StatusCode,
+    pub headers: hyper::header::Headers,
+    pub body: Vec<u8>,
+}
+
+/// A means of sending HTTP requests to the gateway. Transports must be
+/// thread-safe so that a single `Braintree` handle can be shared between
+/// threads.
+pub trait HttpTransport: Send + Sync {
+    fn send(&self, request: Request) -> hyper::Result<Response>;
+}
+
+/// The default transport, backed by a `hyper::Client` using native TLS.
+pub struct HyperTransport {
+    client: hyper::Client,
+}
+
+impl HyperTransport {
+    /// Create a transport using a new `hyper::Client` with native TLS.
+    pub fn new() -> hyper::Result<HyperTransport> {
+        let ssl = hyper_native_tls::NativeTlsClient::new().map_err(|err| hyper::Error::Ssl(Box::new(err)))?;
+        let connector = hyper::net::HttpsConnector::new(ssl);
+        Ok(HyperTransport::with_client(hyper::Client::with_connector(connector)))
+    }
+
+    /// Create a transport using an existing, pre-configured client.
+    pub fn with_client(client: hyper::Client) -> HyperTransport {
+        HyperTransport{client: client}
+    }
+}
+
+impl HttpTransport for HyperTransport {
+    fn send(&self, request: Request) -> hyper::Result<Response> {
+        let mut req = self.client.request(request.method, request.url).headers(request.headers);
+        if let Some(data) = request.body {
+            req = req.body(hyper::client::Body::BufBody(data, data.len()));
+        }
+
+        let mut response = req.send()?;
+        let mut body = Vec::new();
+        response.read_to_end(&mut body)?;
+        Ok(Response{
+            status: response.status,
+            headers: response.headers.clone(),
+            body: body,
+        })
+    }
+}
+
+/// Stand-in transports for tests.
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use libflate;
+    use std::collections::VecDeque;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use Braintree;
+    use Environment;
+
+    /// A transport that answers every request with the same canned response.
+    pub(crate) struct Canned {
+        pub status: hyper::status::StatusCode,
+        pub gzip: bool,
+        pub body: &'static str,
+    }
+
+    impl HttpTransport for Canned {
+        fn send(&self, _request: Request) -> hyper::Result<Response> {
+            let mut headers = hyper::header::Headers::new();
+            let body = if self.gzip {
+                headers.set(hyper::header::ContentEncoding(vec![hyper::header::Encoding::Gzip]));
+                let mut encoder = libflate::gzip::Encoder::new(Vec::new())?;
+                encoder.write_all(self.body.as_bytes())?;
+                encoder.finish().into_result()?
+            } else {
+                self.body.as_bytes().to_vec()
+            };
+            Ok(Response{status: self.status, headers: headers, body: body})
+        }
+    }
+
+    /// A transport that returns each of a series of results in turn, then
+    /// fails any further requests.
+    pub(crate) struct Scripted {
+        results: Mutex<VecDeque<hyper::Result<Response>>>,
+    }
+
+    impl Scripted {
+        pub(crate) fn new(results: Vec<hyper::Result<Response>>) -> Scripted {
+            Scripted{results: Mutex::new(results.into_iter().collect())}
+        }
+
+        /// The number of results that haven't been returned yet.
+        pub(crate) fn remaining(&self) -> usize {
+            self.results.lock().unwrap().len()
+        }
+    }
+
+    impl HttpTransport for Arc<Scripted> {
+        fn send(&self, _request: Request) -> hyper::Result<Response> {
+            self.results.lock().unwrap().pop_front().expect("unexpected request")
+        }
+    }
+
+    /// A sandbox handle that sends its requests to the given transport.
+    pub(crate) fn braintree<T: HttpTransport + 'static>(transport: T) -> Braintree {
+        Braintree::builder()
+            .environment(Environment::Sandbox)
+            .merchant_id("merchant_id")
+            .public_key("public_key")
+            .private_key("private_key")
+            .transport(transport)
+            .build()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{braintree, Canned};
+    use hyper;
+    use Error;
+
+    const TRANSACTION: &str = "<transaction>\
+        <id>abc123</id>\
+        <type>sale</type>\
+        <amount>10.00</amount>\
+        <currency-iso-code>USD</currency-iso-code>\
+        <status>settled</status>\
+        <created-at>2017-01-01T00:00:00Z</created-at>\
+        <updated-at>2017-01-02T00:00:00Z</updated-at>\
+    </transaction>";
+
+    #[test]
+    fn decodes_a_successful_response() {
+        let bt = braintree(Canned{status: hyper::status::StatusCode::Ok, gzip: false, body: TRANSACTION});
+        let transaction = bt.transaction().find(String::from("abc123")).unwrap();
+        assert_eq!(transaction.id, "abc123");
+        assert_eq!(transaction.amount, "10.00");
+    }
+
+    #[test]
+    fn decodes_a_gzipped_response() {
+        let bt = braintree(Canned{status: hyper::status::StatusCode::Ok, gzip: true, body: TRANSACTION});
+        let transaction = bt.transaction().find(String::from("abc123")).unwrap();
+        assert_eq!(transaction.id, "abc123");
+    }
+
+    #[test]
+    fn maps_not_found_to_an_error() {
+        let bt = braintree(Canned{status: hyper::status::StatusCode::NotFound, gzip: false, body: ""});
+        match bt.transaction().find(String::from("missing")) {
+            Err(Error::NotFound) => (),
+            other => panic!("expected Error::NotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_the_body_of_an_api_error() {
+        let body = "<api-error-response><message>Amount is required.</message></api-error-response>";
+        let bt = braintree(Canned{status: hyper::status::StatusCode::UnprocessableEntity, gzip: false, body: body});
+        match bt.transaction().find(String::from("abc123")) {
+            Err(Error::Api(response)) => {
+                assert_eq!(response.message, "Amount is required.");
+                assert_eq!(response.raw, body);
+            },
+            other => panic!("expected Error::Api, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_the_full_path_of_a_missing_element() {
+        let body = "<subscription>\
+            <id>sub123</id>\
+            <status>Active</status>\
+            <add-ons type=\"array\"><add-on><amount>5.00</amount></add-on></add-ons>\
+        </subscription>";
+        let bt = braintree(Canned{status: hyper::status::StatusCode::Ok, gzip: false, body: body});
+        match bt.subscription().find(String::from("sub123")) {
+            Err(Error::Decode{element, body: raw}) => {
+                assert_eq!(element, "subscription/add-ons/add-on/id");
+                assert_eq!(raw, body);
+            },
+            other => panic!("expected Error::Decode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_a_malformed_number() {
+        let body = "<subscription>\
+            <id>sub123</id>\
+            <status>Active</status>\
+            <failure-count>two</failure-count>\
+        </subscription>";
+        let bt = braintree(Canned{status: hyper::status::StatusCode::Ok, gzip: false, body: body});
+        match bt.subscription().find(String::from("sub123")) {
+            Err(Error::Decode{element, ..}) => assert_eq!(element, "subscription/failure-count"),
+            other => panic!("expected Error::Decode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_the_full_path_of_a_malformed_number() {
+        let body = "<add-ons type=\"array\">\
+            <add-on><id>bonus</id><quantity>-1</quantity></add-on>\
+        </add-ons>";
+        let bt = braintree(Canned{status: hyper::status::StatusCode::Ok, gzip: false, body: body});
+        match bt.add_on().all() {
+            Err(Error::Decode{element, ..}) => assert_eq!(element, "add-ons/add-on/quantity"),
+            other => panic!("expected Error::Decode, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Parsing and verification of webhook notifications.
+//!
+//! Braintree sends webhooks as a form POST with two fields, `bt_signature`
+//! and `bt_payload`, which should be passed as-is to
+//! `WebhookNotificationGateway::parse`. The payload is only decoded after its
+//! signature has been checked against your API keys, so a successfully parsed
+//! notification can be trusted to have come from Braintree.
+//!
+//! When a webhook endpoint is registered, Braintree first sends it a GET
+//! request with a `bt_challenge` parameter, which must be answered with the
+//! result of `WebhookNotificationGateway::verify`.
+
+use base64;
+use base64::Engine;
+use elementtree;
+use error;
+use hmac::{Hmac, Mac};
+use sha1::{Digest, Sha1};
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+/// A notification sent to a webhook endpoint.
+#[derive(Debug)]
+pub struct WebhookNotification {
+    pub kind: Kind,
+    pub timestamp: String,
+    pub subject: Subject,
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for WebhookNotification {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<WebhookNotification> {
+        let subject = match root.find("subject") {
+            Some(subject) => match subject.children().next() {
+                Some(child) => ::within(root, ::within(subject, Subject::try_from(child)))?,
+                None => return Err(::Error::Decode{element: String::from("notification/subject"), body: String::new()}),
+            },
+            None => return Err(::Error::Decode{element: String::from("notification/subject"), body: String::new()}),
+        };
+        Ok(WebhookNotification{
+            kind: Kind::from(::require_text(root, "kind")?),
+            timestamp: ::require_text(root, "timestamp")?,
+            subject: subject,
+        })
+    }
+}
+
+/// The record that a notification is about. Records that aren't otherwise
+/// supported by this library are returned as raw XML.
+#[derive(Debug)]
+pub enum Subject {
+    /// The subject of a `Check` notification, sent when testing a webhook
+    /// endpoint from the Control Panel.
+    Check(bool),
+    Subscription(Box<::subscription::Subscription>),
+    Transaction(Box<::transaction::Transaction>),
+    Other {
+        /// The name of the subject's root element, e.g. `dispute`.
+        name: String,
+        /// The subject's XML.
+        xml: String,
+    },
+}
+
+impl<'a> TryFrom<&'a elementtree::Element> for Subject {
+    type Error = ::Error;
+
+    fn try_from(root: &'a elementtree::Element) -> error::Result<Subject> {
+        match root.tag().name() {
+            "check" => Ok(Subject::Check(root.text() == "true")),
+            "subscription" => Ok(Subject::Subscription(Box::new(::subscription::Subscription::try_from(root)?))),
+            "transaction" => Ok(Subject::Transaction(Box::new(::transaction::Transaction::try_from(root)?))),
+            name => Ok(Subject::Other{
+                name: String::from(name),
+                xml: root.to_string().unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+string_enum! {
+    /// The event that triggered a notification.
+    pub enum Kind {
+        AccountUpdaterDailyReport => "account_updater_daily_report",
+        Check => "check",
+        ConnectedMerchantPayPalStatusChanged => "connected_merchant_paypal_status_changed",
+        ConnectedMerchantStatusTransitioned => "connected_merchant_status_transitioned",
+        Disbursement => "disbursement",
+        DisbursementException => "disbursement_exception",
+        DisputeAccepted => "dispute_accepted",
+        DisputeAutoAccepted => "dispute_auto_accepted",
+        DisputeDisputed => "dispute_disputed",
+        DisputeExpired => "dispute_expired",
+        DisputeLost => "dispute_lost",
+        DisputeOpened => "dispute_opened",
+        DisputeUnderReview => "dispute_under_review",
+        DisputeWon => "dispute_won",
+        GrantedPaymentInstrumentUpdate => "granted_payment_instrument_update",
+        GrantedPaymentMethodRevoked => "granted_payment_method_revoked",
+        LocalPaymentCompleted => "local_payment_completed",
+        LocalPaymentReversed => "local_payment_reversed",
+        OAuthAccessRevoked => "oauth_access_revoked",
+        PartnerMerchantConnected => "partner_merchant_connected",
+        PartnerMerchantDeclined => "partner_merchant_declined",
+        PartnerMerchantDisconnected => "partner_merchant_disconnected",
+        PaymentMethodCustomerDataUpdated => "payment_method_customer_data_updated",
+        PaymentMethodRevokedByCustomer => "payment_method_revoked_by_customer",
+        RecipientUpdatedGrantedPaymentMethod => "recipient_updated_granted_payment_method",
+        RefundFailed => "refund_failed",
+        SubMerchantAccountApproved => "sub_merchant_account_approved",
+        SubMerchantAccountDeclined => "sub_merchant_account_declined",
+        SubscriptionCanceled => "subscription_canceled",
+        SubscriptionChargedSuccessfully => "subscription_charged_successfully",
+        SubscriptionChargedUnsuccessfully => "subscription_charged_unsuccessfully",
+        SubscriptionExpired => "subscription_expired",
+        SubscriptionTrialEnded => "subscription_trial_ended",
+        SubscriptionWentActive => "subscription_went_active",
+        SubscriptionWentPastDue => "subscription_went_past_due",
+        TransactionDisbursed => "transaction_disbursed",
+        TransactionSettled => "transaction_settled",
+        TransactionSettlementDeclined => "transaction_settlement_declined",
+    }
+}
+
+/// Verifies `signature` against `payload` and decodes the notification.
+pub(crate) fn parse(public_key: &str, private_key: &str, signature: &str, payload: &str) -> error::Result<WebhookNotification> {
+    if payload.is_empty() || !payload.bytes().all(|b| b.is_ascii_alphanumeric() || b"+/=\n".contains(&b)) {
+        return Err(::Error::InvalidSignature);
+    }
+    let signature = signature.split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '|');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(signature)) if key == public_key => Some(signature),
+                _ => None,
+            }
+        })
+        .next()
+        .ok_or(::Error::InvalidSignature)?;
+    let matches = constant_time_eq(signature.as_bytes(), sign(private_key, payload.as_bytes()).as_bytes())
+        || constant_time_eq(signature.as_bytes(), sign(private_key, format!("{}\n", payload).as_bytes()).as_bytes());
+    if !matches {
+        return Err(::Error::InvalidSignature);
+    }
+
+    let stripped: String = payload.chars().filter(|&c| c != '\n').collect();
+    let xml = match base64::engine::general_purpose::STANDARD.decode(&stripped) {
+        Ok(xml) => xml,
+        Err(_) => return Err(::Error::Decode{element: String::new(), body: String::from(payload)}),
+    };
+    ::decode(Box::new(Cursor::new(xml)), |root| WebhookNotification::try_from(root))
+}
+
+/// The response to a webhook challenge, which is the challenge signed with
+/// the private key. Challenges are always 20 to 32 lowercase hex digits.
+pub(crate) fn verify(public_key: &str, private_key: &str, challenge: &str) -> error::Result<String> {
+    let valid = (20..=32).contains(&challenge.len())
+        && challenge.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    if !valid {
+        return Err(::Error::InvalidChallenge);
+    }
+    Ok(format!("{}|{}", public_key, sign(private_key, challenge.as_bytes())))
+}
+
+/// The hex-encoded HMAC-SHA1 of `data`, keyed with the SHA1 digest of the
+/// private key.
+pub(crate) fn sign(private_key: &str, data: &[u8]) -> String {
+    let key = Sha1::digest(private_key.as_bytes());
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(&key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "public_key";
+    const PRIVATE_KEY: &str = "private_key";
+    const XML: &str = "<notification>\
+        <kind>check</kind>\
+        <timestamp>2017-01-01T00:00:00Z</timestamp>\
+        <subject><check type=\"boolean\">true</check></subject>\
+    </notification>";
+
+    fn payload() -> String {
+        base64::engine::general_purpose::STANDARD.encode(XML)
+    }
+
+    fn signature(payload: &str) -> String {
+        format!("{}|{}", PUBLIC_KEY, sign(PRIVATE_KEY, payload.as_bytes()))
+    }
+
+    fn assert_invalid(result: error::Result<WebhookNotification>) {
+        match result {
+            Err(::Error::InvalidSignature) => (),
+            other => panic!("expected Error::InvalidSignature, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_a_correctly_signed_payload() {
+        let payload = payload();
+        let notification = parse(PUBLIC_KEY, PRIVATE_KEY, &signature(&payload), &payload).unwrap();
+        assert_eq!(notification.kind, Kind::Check);
+        assert_eq!(notification.timestamp, "2017-01-01T00:00:00Z");
+        match notification.subject {
+            Subject::Check(true) => (),
+            other => panic!("unexpected subject {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_a_tampered_payload() {
+        let payload = payload();
+        let tampered = base64::engine::general_purpose::STANDARD.encode(XML.replace("true", "false"));
+        assert_invalid(parse(PUBLIC_KEY, PRIVATE_KEY, &signature(&payload), &tampered));
+    }
+
+    #[test]
+    fn rejects_a_signature_for_another_public_key() {
+        let payload = payload();
+        let signature = format!("other_key|{}", sign(PRIVATE_KEY, payload.as_bytes()));
+        assert_invalid(parse(PUBLIC_KEY, PRIVATE_KEY, &signature, &payload));
+    }
+
+    #[test]
+    fn rejects_a_malformed_signature() {
+        let payload = payload();
+        assert_invalid(parse(PUBLIC_KEY, PRIVATE_KEY, "", &payload));
+        assert_invalid(parse(PUBLIC_KEY, PRIVATE_KEY, PUBLIC_KEY, &payload));
+        assert_invalid(parse(PUBLIC_KEY, PRIVATE_KEY, &format!("{}|", PUBLIC_KEY), &payload));
+        assert_invalid(parse(PUBLIC_KEY, PRIVATE_KEY, &format!("{}|not-hex", PUBLIC_KEY), &payload));
+    }
+
+    #[test]
+    fn accepts_newline_wrapped_base64() {
+        let payload = payload().as_bytes()
+            .chunks(60)
+            .map(|line| String::from_utf8(line.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n") + "\n";
+        assert!(payload.matches('\n').count() > 1);
+        let notification = parse(PUBLIC_KEY, PRIVATE_KEY, &signature(&payload), &payload).unwrap();
+        assert_eq!(notification.kind, Kind::Check);
+    }
+
+    #[test]
+    fn accepts_a_signature_with_a_trailing_newline() {
+        let payload = payload();
+        let signature = format!("{}|{}", PUBLIC_KEY, sign(PRIVATE_KEY, format!("{}\n", payload).as_bytes()));
+        assert!(parse(PUBLIC_KEY, PRIVATE_KEY, &signature, &payload).is_ok());
+    }
+
+    #[test]
+    fn finds_the_matching_pair_in_a_multi_key_signature() {
+        let payload = payload();
+        let signature = format!("other_key|{}&{}", sign("other_private_key", payload.as_bytes()), signature(&payload));
+        assert!(parse(PUBLIC_KEY, PRIVATE_KEY, &signature, &payload).is_ok());
+    }
+
+    #[test]
+    fn answers_valid_challenges() {
+        let response = verify(PUBLIC_KEY, PRIVATE_KEY, "20f9f8ed05f77439fe955c977e4c8a53").unwrap();
+        assert_eq!(response, format!("{}|{}", PUBLIC_KEY, sign(PRIVATE_KEY, b"20f9f8ed05f77439fe955c977e4c8a53")));
+    }
+
+    #[test]
+    fn rejects_invalid_challenges() {
+        for challenge in &["", "0123456789abcdef012", "0123456789abcdef0123456789abcdef0", "0123456789ABCDEF0123", "0123456789abcdefghij"] {
+            match verify(PUBLIC_KEY, PRIVATE_KEY, challenge) {
+                Err(::Error::InvalidChallenge) => (),
+                other => panic!("expected Error::InvalidChallenge for {:?}, got {:?}", challenge, other),
+            }
+        }
+    }
+}
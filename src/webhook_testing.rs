@@ -0,0 +1,348 @@
+//! Generation of sample webhook notifications, for testing webhook handlers
+//! without a live gateway.
+//!
+//! The generated notifications are signed with your API keys, so they can be
+//! passed straight to `WebhookNotificationGateway::parse` or posted to your
+//! own endpoint as the `bt_signature` and `bt_payload` fields.
+
+use base64;
+use base64::Engine;
+use std::time::{SystemTime, UNIX_EPOCH};
+use webhook_notification::{self, Kind};
+use xml;
+
+/// Builds a signed `(bt_signature, bt_payload)` pair for a notification of
+/// the given kind, whose subject has the given id.
+pub(crate) fn sample_notification(public_key: &str, private_key: &str, kind: Kind, id: &str) -> (String, String) {
+    let id = xml::escape(id);
+    let notification = format!(
+        "<notification><timestamp type=\"datetime\">{}</timestamp><kind>{}</kind><subject>{}</subject></notification>",
+        timestamp(),
+        xml::escape(&String::from(kind.clone())),
+        subject_xml(&kind, &id),
+    );
+
+    // The official SDKs wrap the payload at 60 characters, so do the same to
+    // make sure that handlers cope with it.
+    let encoded = base64::engine::general_purpose::STANDARD.encode(notification.as_bytes());
+    let mut payload = String::new();
+    for line in encoded.as_bytes().chunks(60) {
+        payload.push_str(::std::str::from_utf8(line).unwrap());
+        payload.push('\n');
+    }
+    let signature = format!("{}|{}", public_key, webhook_notification::sign(private_key, payload.as_bytes()));
+    (signature, payload)
+}
+
+fn subject_xml(kind: &Kind, id: &str) -> String {
+    match *kind {
+        Kind::Check => String::from("<check type=\"boolean\">true</check>"),
+        Kind::AccountUpdaterDailyReport => String::from(
+            "<account-updater-daily-report>\
+                <report-date type=\"date\">2016-01-14</report-date>\
+                <report-url>link-to-csv-report</report-url>\
+            </account-updater-daily-report>"),
+        Kind::ConnectedMerchantPayPalStatusChanged => format!(
+            "<connected-merchant-paypal-status-changed>\
+                <merchant-public-id>{}</merchant-public-id>\
+                <oauth-application-client-id>oauth_application_client_id</oauth-application-client-id>\
+                <action>link</action>\
+            </connected-merchant-paypal-status-changed>", id),
+        Kind::ConnectedMerchantStatusTransitioned => format!(
+            "<connected-merchant-status-transitioned>\
+                <merchant-public-id>{}</merchant-public-id>\
+                <oauth-application-client-id>oauth_application_client_id</oauth-application-client-id>\
+                <status>new_status</status>\
+            </connected-merchant-status-transitioned>", id),
+        Kind::Disbursement => disbursement_xml(id, true),
+        Kind::DisbursementException => disbursement_xml(id, false),
+        Kind::DisputeAccepted => dispute_xml(id, "accepted"),
+        Kind::DisputeAutoAccepted => dispute_xml(id, "auto_accepted"),
+        Kind::DisputeDisputed => dispute_xml(id, "disputed"),
+        Kind::DisputeExpired => dispute_xml(id, "expired"),
+        Kind::DisputeLost => dispute_xml(id, "lost"),
+        Kind::DisputeOpened => dispute_xml(id, "open"),
+        Kind::DisputeUnderReview => dispute_xml(id, "under_review"),
+        Kind::DisputeWon => dispute_xml(id, "won"),
+        Kind::GrantedPaymentInstrumentUpdate|Kind::RecipientUpdatedGrantedPaymentMethod => format!(
+            "<granted-payment-instrument-update>\
+                <grant-owner-merchant-id>vczo7jqrpwrsi2px</grant-owner-merchant-id>\
+                <grant-recipient-merchant-id>cf0i8wgarszuy6hc</grant-recipient-merchant-id>\
+                <payment-method-nonce><nonce>ee257d98-de40-47e8-96b3-a6954ea7a9a4</nonce><consumed type=\"boolean\">false</consumed><locked type=\"boolean\">false</locked></payment-method-nonce>\
+                <token>{}</token>\
+                <updated-fields type=\"array\"><item>expiration-month</item><item>expiration-year</item></updated-fields>\
+            </granted-payment-instrument-update>", id),
+        Kind::GrantedPaymentMethodRevoked => format!(
+            "<venmo-account>\
+                <created-at type=\"datetime\">2018-10-11T21:28:37Z</created-at>\
+                <updated-at type=\"datetime\">2018-10-11T21:28:37Z</updated-at>\
+                <default type=\"boolean\">true</default>\
+                <image-url>https://assets.braintreegateway.com/payment_method_logo/venmo.png?environment=test</image-url>\
+                <token>{}</token>\
+                <source-description>Venmo Account: venmojoe</source-description>\
+                <username>venmojoe</username>\
+                <venmo-user-id>456</venmo-user-id>\
+                <subscriptions type=\"array\"/>\
+                <customer-id>venmo_customer_id</customer-id>\
+                <global-id>cGF5bWVudG1ldGhvZF92ZW5tb2FjY291bnQ</global-id>\
+            </venmo-account>", id),
+        Kind::LocalPaymentCompleted => format!(
+            "<local-payment>\
+                <payment-id>{}</payment-id>\
+                <payer-id>ABCPAYER</payer-id>\
+                <payment-method-nonce>ee257d98-de40-47e8-96b3-a6954ea7a9a4</payment-method-nonce>\
+                {}\
+            </local-payment>", id, transaction_xml(id, "sale", "authorized", None)),
+        Kind::LocalPaymentReversed => format!(
+            "<local-payment-reversed>\
+                <payment-id>{}</payment-id>\
+            </local-payment-reversed>", id),
+        Kind::OAuthAccessRevoked => format!(
+            "<oauth-application-revocation>\
+                <merchant-id>{}</merchant-id>\
+                <oauth-application-client-id>oauth_application_client_id</oauth-application-client-id>\
+            </oauth-application-revocation>", id),
+        Kind::PartnerMerchantConnected => String::from(
+            "<partner-merchant>\
+                <partner-merchant-id>abc123</partner-merchant-id>\
+                <merchant-public-id>public_id</merchant-public-id>\
+                <public-key>public_key</public-key>\
+                <private-key>private_key</private-key>\
+                <client-side-encryption-key>cse_key</client-side-encryption-key>\
+            </partner-merchant>"),
+        Kind::PartnerMerchantDeclined|Kind::PartnerMerchantDisconnected => String::from(
+            "<partner-merchant>\
+                <partner-merchant-id>abc123</partner-merchant-id>\
+            </partner-merchant>"),
+        Kind::PaymentMethodCustomerDataUpdated => format!(
+            "<payment-method-customer-data-updated-metadata>\
+                <token>TOKEN-12345</token>\
+                <payment-method>{}</payment-method>\
+                <datetime-updated type=\"datetime\">2022-01-01T21:28:37Z</datetime-updated>\
+                <enriched-customer-data>\
+                    <fields-updated type=\"array\"><item>username</item></fields-updated>\
+                    <profile-data><username>venmo_username</username><first-name>John</first-name><last-name>Doe</last-name></profile-data>\
+                </enriched-customer-data>\
+            </payment-method-customer-data-updated-metadata>", subject_xml(&Kind::GrantedPaymentMethodRevoked, id)),
+        Kind::PaymentMethodRevokedByCustomer => format!(
+            "<paypal-account>\
+                <billing-agreement-id>a-billing-agreement-id</billing-agreement-id>\
+                <created-at type=\"datetime\">2019-01-01T12:00:00Z</created-at>\
+                <customer-id>a-customer-id</customer-id>\
+                <default type=\"boolean\">true</default>\
+                <email>name@email.com</email>\
+                <global-id>cGF5bWVudG1ldGhvZF9jaDZieXNz</global-id>\
+                <image-url>https://assets.braintreegateway.com/payment_method_logo/paypal.png?environment=test</image-url>\
+                <subscriptions type=\"array\"/>\
+                <token>{}</token>\
+                <updated-at type=\"datetime\">2019-01-02T12:00:00Z</updated-at>\
+                <is-channel-initiated nil=\"true\"/>\
+                <payer-id>a-payer-id</payer-id>\
+                <payer-info nil=\"true\"/>\
+                <limited-use-order-id nil=\"true\"/>\
+                <revoked-at type=\"datetime\">2019-01-02T12:00:00Z</revoked-at>\
+            </paypal-account>", id),
+        Kind::RefundFailed => transaction_xml(id, "credit", "processor_declined", None),
+        Kind::SubMerchantAccountApproved => format!(
+            "<merchant-account>\
+                <id>{}</id>\
+                <status>active</status>\
+                <master-merchant-account><id>master_ma_for_{}</id><status>active</status></master-merchant-account>\
+            </merchant-account>", id, id),
+        Kind::SubMerchantAccountDeclined => format!(
+            "<api-error-response>\
+                <message>Applicant declined due to OFAC.</message>\
+                <errors>\
+                    <merchant-account>\
+                        <errors type=\"array\">\
+                            <error><code>82621</code><message>Applicant declined due to OFAC.</message><attribute type=\"symbol\">base</attribute></error>\
+                        </errors>\
+                    </merchant-account>\
+                </errors>\
+                <merchant-account>\
+                    <id>{}</id>\
+                    <status>suspended</status>\
+                    <master-merchant-account><id>master_ma_for_{}</id><status>suspended</status></master-merchant-account>\
+                </merchant-account>\
+            </api-error-response>", id, id),
+        Kind::TransactionDisbursed => transaction_xml(id, "sale", "settled", Some(
+            "<disbursement-details><disbursement-date type=\"date\">2013-07-09</disbursement-date><success type=\"boolean\">true</success></disbursement-details>")),
+        Kind::TransactionSettled => transaction_xml(id, "sale", "settled", None),
+        Kind::TransactionSettlementDeclined => transaction_xml(id, "sale", "settlement_declined", None),
+        Kind::SubscriptionChargedSuccessfully => subscription_xml(id, "active", Some(transaction_xml(id, "sale", "submitted_for_settlement", None))),
+        Kind::SubscriptionChargedUnsuccessfully => subscription_xml(id, "past_due", Some(transaction_xml(id, "sale", "failed", None))),
+        Kind::SubscriptionCanceled => subscription_xml(id, "canceled", None),
+        Kind::SubscriptionExpired => subscription_xml(id, "expired", None),
+        Kind::SubscriptionWentPastDue => subscription_xml(id, "past_due", None),
+        Kind::SubscriptionTrialEnded|Kind::SubscriptionWentActive|Kind::Unknown(_) => subscription_xml(id, "active", None),
+    }
+}
+
+fn transaction_xml(id: &str, typ: &str, status: &str, extra: Option<&str>) -> String {
+    format!(
+        "<transaction>\
+            <id>{}</id>\
+            <type>{}</type>\
+            <amount>100.00</amount>\
+            <currency-iso-code>USD</currency-iso-code>\
+            <status>{}</status>\
+            <created-at type=\"datetime\">2013-07-09T18:23:29Z</created-at>\
+            <updated-at type=\"datetime\">2013-07-09T18:23:29Z</updated-at>\
+            <merchant-account-id>sandbox_merchant_account</merchant-account-id>\
+            <billing><postal-code>60637</postal-code></billing>\
+            {}\
+        </transaction>", id, typ, status, extra.unwrap_or(""))
+}
+
+fn subscription_xml(id: &str, status: &str, transaction: Option<String>) -> String {
+    format!(
+        "<subscription>\
+            <id>{}</id>\
+            <status>{}</status>\
+            <transactions type=\"array\">{}</transactions>\
+            <add-ons type=\"array\"></add-ons>\
+            <discounts type=\"array\"></discounts>\
+        </subscription>", id, status, transaction.unwrap_or_default())
+}
+
+fn dispute_xml(id: &str, status: &str) -> String {
+    format!(
+        "<dispute>\
+            <id>{}</id>\
+            <amount>100.00</amount>\
+            <amount-disputed>100.00</amount-disputed>\
+            <amount-won>95.00</amount-won>\
+            <currency-iso-code>USD</currency-iso-code>\
+            <kind>chargeback</kind>\
+            <reason>fraud</reason>\
+            <status>{}</status>\
+            <received-date type=\"date\">2014-03-01</received-date>\
+            <reply-by-date type=\"date\">2014-03-21</reply-by-date>\
+            <date-opened type=\"date\">2014-03-28</date-opened>\
+            <transaction><id>{}</id><amount>100.00</amount></transaction>\
+        </dispute>", id, status, id)
+}
+
+fn disbursement_xml(id: &str, success: bool) -> String {
+    let (exception_message, follow_up_action) = if success { ("", "") } else { ("bank_rejected", "update_funding_information") };
+    format!(
+        "<disbursement>\
+            <id>{}</id>\
+            <transaction-ids type=\"array\"><item>afv56j</item><item>kj8hjk</item></transaction-ids>\
+            <success type=\"boolean\">{}</success>\
+            <retry type=\"boolean\">false</retry>\
+            <merchant-account>\
+                <id>merchant_account_token</id>\
+                <currency-iso-code>USD</currency-iso-code>\
+                <sub-merchant-account type=\"boolean\">false</sub-merchant-account>\
+                <status>active</status>\
+            </merchant-account>\
+            <amount>100.00</amount>\
+            <disbursement-date type=\"date\">2014-02-10</disbursement-date>\
+            <exception-message>{}</exception-message>\
+            <follow-up-action>{}</follow-up-action>\
+        </disbursement>", id, success, exception_message, follow_up_action)
+}
+
+/// The current time, formatted like the gateway's datetime values.
+fn timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+
+    // Convert days since the epoch to a civil date. See
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elementtree;
+    use webhook_notification::Subject;
+
+    const ID: &str = "sample_id_42";
+
+    fn kinds() -> Vec<Kind> {
+        vec![
+            Kind::AccountUpdaterDailyReport,
+            Kind::Check,
+            Kind::ConnectedMerchantPayPalStatusChanged,
+            Kind::ConnectedMerchantStatusTransitioned,
+            Kind::Disbursement,
+            Kind::DisbursementException,
+            Kind::DisputeAccepted,
+            Kind::DisputeAutoAccepted,
+            Kind::DisputeDisputed,
+            Kind::DisputeExpired,
+            Kind::DisputeLost,
+            Kind::DisputeOpened,
+            Kind::DisputeUnderReview,
+            Kind::DisputeWon,
+            Kind::GrantedPaymentInstrumentUpdate,
+            Kind::GrantedPaymentMethodRevoked,
+            Kind::LocalPaymentCompleted,
+            Kind::LocalPaymentReversed,
+            Kind::OAuthAccessRevoked,
+            Kind::PartnerMerchantConnected,
+            Kind::PartnerMerchantDeclined,
+            Kind::PartnerMerchantDisconnected,
+            Kind::PaymentMethodCustomerDataUpdated,
+            Kind::PaymentMethodRevokedByCustomer,
+            Kind::RecipientUpdatedGrantedPaymentMethod,
+            Kind::RefundFailed,
+            Kind::SubMerchantAccountApproved,
+            Kind::SubMerchantAccountDeclined,
+            Kind::SubscriptionCanceled,
+            Kind::SubscriptionChargedSuccessfully,
+            Kind::SubscriptionChargedUnsuccessfully,
+            Kind::SubscriptionExpired,
+            Kind::SubscriptionTrialEnded,
+            Kind::SubscriptionWentActive,
+            Kind::SubscriptionWentPastDue,
+            Kind::TransactionDisbursed,
+            Kind::TransactionSettled,
+            Kind::TransactionSettlementDeclined,
+            Kind::Unknown(String::from("some_future_kind")),
+        ]
+    }
+
+    #[test]
+    fn sample_notifications_parse_back_to_the_same_kind_and_subject() {
+        for kind in kinds() {
+            let (signature, payload) = sample_notification("public_key", "private_key", kind.clone(), ID);
+            let notification = webhook_notification::parse("public_key", "private_key", &signature, &payload)
+                .unwrap_or_else(|err| panic!("failed to parse {:?}: {:?}", kind, err));
+            assert_eq!(notification.kind, kind);
+
+            let expected = elementtree::Element::from_reader(subject_xml(&kind, ID).as_bytes()).unwrap();
+            match notification.subject {
+                Subject::Check(checked) => {
+                    assert_eq!(kind, Kind::Check);
+                    assert!(checked);
+                },
+                Subject::Subscription(subscription) => {
+                    assert_eq!(expected.tag().name(), "subscription", "{:?}", kind);
+                    assert_eq!(subscription.id, ID);
+                },
+                Subject::Transaction(transaction) => {
+                    assert_eq!(expected.tag().name(), "transaction", "{:?}", kind);
+                    assert_eq!(transaction.id, ID);
+                },
+                Subject::Other{name, xml} => {
+                    assert_eq!(name, expected.tag().name(), "{:?}", kind);
+                    assert_eq!(xml.contains(ID), subject_xml(&kind, ID).contains(ID), "{:?}", kind);
+                },
+            }
+        }
+    }
+}